@@ -0,0 +1,139 @@
+//! AES with the AES-NI instructions of x86_64, and GHASH multiplication
+//! with PCLMULQDQ.
+//!
+//! The functions here may only be called once `Backend::AesNi` has been
+//! found to be available.
+
+use std::arch::x86_64::*;
+
+// The round keys as stored by `key_expansion`, one block per round.
+unsafe fn load_keys(nr: usize, round_key: &[u8]) -> [__m128i; 15] {
+    let mut keys = [_mm_setzero_si128(); 15];
+    for (key, bytes) in keys.iter_mut().zip(round_key.chunks(16)).take(nr + 1) {
+        *key = _mm_loadu_si128(bytes.as_ptr() as *const __m128i);
+    }
+    keys
+}
+
+// Four blocks are processed together to hide the latency of the round
+// instructions.
+#[target_feature(enable = "aes")]
+unsafe fn encrypt_blocks(nr: usize, blocks: &mut [u8], round_key: &[u8]) {
+    let keys = load_keys(nr, round_key);
+
+    let mut groups = blocks.chunks_exact_mut(64);
+    for group in &mut groups {
+        let p = group.as_mut_ptr() as *mut __m128i;
+        let mut b = [_mm_setzero_si128(); 4];
+        for (i, b) in b.iter_mut().enumerate() {
+            *b = _mm_xor_si128(_mm_loadu_si128(p.add(i)), keys[0]);
+        }
+        for key in &keys[1..nr] {
+            for b in b.iter_mut() {
+                *b = _mm_aesenc_si128(*b, *key);
+            }
+        }
+        for (i, b) in b.iter().enumerate() {
+            _mm_storeu_si128(p.add(i), _mm_aesenclast_si128(*b, keys[nr]));
+        }
+    }
+
+    for block in groups.into_remainder().chunks_exact_mut(16) {
+        let p = block.as_mut_ptr() as *mut __m128i;
+        let mut b = _mm_xor_si128(_mm_loadu_si128(p), keys[0]);
+        for key in &keys[1..nr] {
+            b = _mm_aesenc_si128(b, *key);
+        }
+        _mm_storeu_si128(p, _mm_aesenclast_si128(b, keys[nr]));
+    }
+}
+
+// The equivalent inverse cipher of FIPS 197 5.3.5, which uses the round
+// keys in reverse order with InvMixColumns applied to the middle ones.
+#[target_feature(enable = "aes")]
+unsafe fn decrypt_blocks(nr: usize, blocks: &mut [u8], round_key: &[u8]) {
+    let enc = load_keys(nr, round_key);
+    let mut keys = [_mm_setzero_si128(); 15];
+    keys[0] = enc[nr];
+    for i in 1..nr {
+        keys[i] = _mm_aesimc_si128(enc[nr - i]);
+    }
+    keys[nr] = enc[0];
+
+    let mut groups = blocks.chunks_exact_mut(64);
+    for group in &mut groups {
+        let p = group.as_mut_ptr() as *mut __m128i;
+        let mut b = [_mm_setzero_si128(); 4];
+        for (i, b) in b.iter_mut().enumerate() {
+            *b = _mm_xor_si128(_mm_loadu_si128(p.add(i)), keys[0]);
+        }
+        for key in &keys[1..nr] {
+            for b in b.iter_mut() {
+                *b = _mm_aesdec_si128(*b, *key);
+            }
+        }
+        for (i, b) in b.iter().enumerate() {
+            _mm_storeu_si128(p.add(i), _mm_aesdeclast_si128(*b, keys[nr]));
+        }
+    }
+
+    for block in groups.into_remainder().chunks_exact_mut(16) {
+        let p = block.as_mut_ptr() as *mut __m128i;
+        let mut b = _mm_xor_si128(_mm_loadu_si128(p), keys[0]);
+        for key in &keys[1..nr] {
+            b = _mm_aesdec_si128(b, *key);
+        }
+        _mm_storeu_si128(p, _mm_aesdeclast_si128(b, keys[nr]));
+    }
+}
+
+// With the word in all four columns ShiftRows has no effect, so the last
+// round with a zero key is just SubBytes.
+#[target_feature(enable = "aes")]
+unsafe fn sub_word_aesni(word: u32) -> u32 {
+    let x = _mm_set1_epi32(word as i32);
+    _mm_cvtsi128_si32(_mm_aesenclast_si128(x, _mm_setzero_si128())) as u32
+}
+
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn clmul(a: u64, b: u64) -> u128 {
+    let a = _mm_cvtsi64_si128(a as i64);
+    let b = _mm_cvtsi64_si128(b as i64);
+    let mut out = [0u8; 16];
+    _mm_storeu_si128(
+        out.as_mut_ptr() as *mut __m128i,
+        _mm_clmulepi64_si128(a, b, 0),
+    );
+    u128::from_le_bytes(out)
+}
+
+pub(crate) fn encrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    unsafe { encrypt_blocks(nr as usize, blocks, round_key) }
+}
+
+pub(crate) fn decrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    unsafe { decrypt_blocks(nr as usize, blocks, round_key) }
+}
+
+pub(crate) fn sub_word(word: &mut [u8; 4]) {
+    *word = unsafe { sub_word_aesni(u32::from_le_bytes(*word)) }.to_le_bytes();
+}
+
+// The GCM multiplication of `gcm::gf_mul`. Reversing the bits turns the
+// reflected blocks into ordinary polynomials, which are multiplied and then
+// reduced modulo x^128 + x^7 + x^2 + x + 1.
+pub(crate) fn gf_mul(x: u128, y: u128) -> u128 {
+    let (x, y) = (x.reverse_bits(), y.reverse_bits());
+    let (x1, x0) = ((x >> 64) as u64, x as u64);
+    let (y1, y0) = ((y >> 64) as u64, y as u64);
+
+    let (lo, mid, hi) = unsafe { (clmul(x0, y0), clmul(x0, y1) ^ clmul(x1, y0), clmul(x1, y1)) };
+    let lo = lo ^ (mid << 64);
+    let hi = hi ^ (mid >> 64);
+
+    // x^128 = x^7 + x^2 + x + 1, folding the bits shifted out twice.
+    let carry = (hi >> 127) ^ (hi >> 126) ^ (hi >> 121);
+    let hi = hi ^ carry;
+    let z = lo ^ hi ^ (hi << 1) ^ (hi << 2) ^ (hi << 7);
+    z.reverse_bits()
+}
@@ -0,0 +1,112 @@
+//! Runtime selection of the code that runs the block cipher.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use super::Error;
+
+/// The code that runs the block cipher, chosen once per process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The byte-oriented code with S-box tables. Table lookups with secret
+    /// indices can leak through cache timing.
+    Soft,
+    /// Constant-time bitsliced code, four blocks at a time.
+    Bitslice,
+    /// The AES-NI instructions of x86_64, with PCLMULQDQ for GHASH.
+    AesNi,
+    /// AES-NI with VAES for up to 16 blocks at a time, on AVX-512 registers
+    /// if the CPU has them and on AVX2 registers otherwise.
+    Vaes,
+    /// 32-bit T-tables, faster than `Soft` on machines without AES
+    /// instructions.
+    ///
+    /// Not constant-time: the table lookups are indexed by secret state and
+    /// key bytes, so which cache lines they load leaks key bits to code
+    /// that can measure cache timing on the same machine. Needs the
+    /// `ttable` feature and is never picked automatically, only by
+    /// `Backend::select`.
+    TTable,
+    /// Constant-time code with the SSSE3 byte shuffle, for x86_64 without
+    /// AES-NI.
+    Vperm,
+}
+
+// 0 until the first block is processed, then the selected backend + 1.
+static SELECTED: AtomicU8 = AtomicU8::new(0);
+
+const ALL: [Backend; 6] = [
+    Backend::Soft,
+    Backend::Bitslice,
+    Backend::AesNi,
+    Backend::Vaes,
+    Backend::TTable,
+    Backend::Vperm,
+];
+
+impl Backend {
+    /// Whether this backend can run on this machine.
+    pub fn is_available(self) -> bool {
+        match self {
+            Backend::Soft | Backend::Bitslice => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::AesNi => is_x86_feature_detected!("aes"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Vaes => {
+                is_x86_feature_detected!("aes")
+                    && is_x86_feature_detected!("avx2")
+                    && is_x86_feature_detected!("vaes")
+            }
+            #[cfg(target_arch = "x86_64")]
+            Backend::Vperm => is_x86_feature_detected!("ssse3"),
+            #[cfg(not(target_arch = "x86_64"))]
+            Backend::AesNi | Backend::Vaes | Backend::Vperm => false,
+            Backend::TTable => cfg!(feature = "ttable"),
+        }
+    }
+
+    /// The backend that is used unless another one is selected: VAES,
+    /// AES-NI or the SSSE3 code if the CPU supports it, otherwise the
+    /// bitsliced code if the `bitslice` feature is enabled and the
+    /// table-based code if not. `TTable` is never chosen here.
+    pub fn detect() -> Backend {
+        if Backend::Vaes.is_available() {
+            Backend::Vaes
+        } else if Backend::AesNi.is_available() {
+            Backend::AesNi
+        } else if Backend::Vperm.is_available() {
+            Backend::Vperm
+        } else if cfg!(feature = "bitslice") {
+            Backend::Bitslice
+        } else {
+            Backend::Soft
+        }
+    }
+
+    /// The backend used by all contexts in this process.
+    pub fn selected() -> Backend {
+        match SELECTED.load(Ordering::Relaxed) {
+            0 => {
+                let backend = Backend::detect();
+                SELECTED.store(backend as u8 + 1, Ordering::Relaxed);
+                backend
+            }
+            n => ALL[n as usize - 1],
+        }
+    }
+
+    /// Makes all contexts in this process use this backend, returning
+    /// `Error::UnsupportedBackend` if it is not available. All backends
+    /// give the same results, so this is mostly useful for testing and
+    /// benchmarking.
+    ///
+    /// Selecting `Backend::TTable` trades the timing safety of the default
+    /// for speed: T-table lookups leak key bits through cache timing, which
+    /// is why it is never picked automatically.
+    pub fn select(self) -> Result<(), Error> {
+        if !self.is_available() {
+            return Err(Error::UnsupportedBackend);
+        }
+        SELECTED.store(self as u8 + 1, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+//! Bitsliced AES without any table lookups or branches on secret data.
+//!
+//! Four blocks are processed at once. Their 64 bytes are spread over eight
+//! 64-bit words, word j holding bit j of every byte, with byte i of block b
+//! at bit 16 * b + i. The S-box is computed as the inverse in GF(2^8),
+//! x^254, followed by the affine transformation of FIPS 197.
+
+type State = [u64; 8];
+
+// The bits of byte 0 of every block.
+const BLOCKS: u64 = 0x0001_0001_0001_0001;
+
+fn pack(bytes: &[u8; 64]) -> State {
+    let mut state = [0u64; 8];
+    for (i, b) in bytes.iter().enumerate() {
+        for (j, word) in state.iter_mut().enumerate() {
+            *word |= ((*b as u64 >> j) & 1) << i;
+        }
+    }
+    state
+}
+
+fn unpack(state: &State, bytes: &mut [u8; 64]) {
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = 0;
+        for (j, word) in state.iter().enumerate() {
+            *b |= (((word >> i) & 1) as u8) << j;
+        }
+    }
+}
+
+fn mul(a: &State, b: &State) -> State {
+    let mut c = [0u64; 15];
+    for i in 0..8 {
+        for j in 0..8 {
+            c[i + j] ^= a[i] & b[j];
+        }
+    }
+    // x^8 = x^4 + x^3 + x + 1
+    for k in (8..15).rev() {
+        c[k - 4] ^= c[k];
+        c[k - 5] ^= c[k];
+        c[k - 7] ^= c[k];
+        c[k - 8] ^= c[k];
+    }
+    let mut out = [0u64; 8];
+    out.copy_from_slice(&c[..8]);
+    out
+}
+
+// x^254, which is the inverse of x and maps 0 to 0.
+fn invert(x: &State) -> State {
+    let x2 = mul(x, x);
+    let x3 = mul(&x2, x);
+    let x6 = mul(&x3, &x3);
+    let x12 = mul(&x6, &x6);
+    let x15 = mul(&x12, &x3);
+    let mut x240 = x15;
+    for _ in 0..4 {
+        x240 = mul(&x240, &x240);
+    }
+    let x252 = mul(&x240, &x12);
+    mul(&x252, &x2)
+}
+
+fn sub_bytes(state: &mut State) {
+    let x = invert(state);
+    for i in 0..8 {
+        state[i] = x[i] ^ x[(i + 4) % 8] ^ x[(i + 5) % 8] ^ x[(i + 6) % 8] ^ x[(i + 7) % 8];
+    }
+    // Add 0x63.
+    for &i in [0, 1, 5, 6].iter() {
+        state[i] = !state[i];
+    }
+}
+
+fn inv_sub_bytes(state: &mut State) {
+    let s = *state;
+    for i in 0..8 {
+        state[i] = s[(i + 2) % 8] ^ s[(i + 5) % 8] ^ s[(i + 7) % 8];
+    }
+    // Add 0x05.
+    for &i in [0, 2].iter() {
+        state[i] = !state[i];
+    }
+    *state = invert(state);
+}
+
+// Byte c * 4 + r of a block is row r of column c.
+fn shift_rows(state: &mut State, inverse: bool) {
+    for word in state.iter_mut() {
+        let mut out = 0;
+        for c in 0..4 {
+            for r in 0..4 {
+                let (dst, src) = (c * 4 + r, ((c + r) % 4) * 4 + r);
+                let (dst, src) = if inverse { (src, dst) } else { (dst, src) };
+                out |= ((*word >> src) & BLOCKS) << dst;
+            }
+        }
+        *word = out;
+    }
+}
+
+// Row r of every column takes the value of row r + n.
+fn rotate_rows(word: u64, n: u32) -> u64 {
+    let low = 0x1111_1111_1111_1111u64 * ((1 << (4 - n)) - 1);
+    ((word >> n) & low) | ((word << (4 - n)) & !low)
+}
+
+fn xtime(s: &State) -> State {
+    [
+        s[7],
+        s[0] ^ s[7],
+        s[1],
+        s[2] ^ s[7],
+        s[3] ^ s[7],
+        s[4],
+        s[5],
+        s[6],
+    ]
+}
+
+// Row r becomes 2 a_r + 3 a_{r+1} + a_{r+2} + a_{r+3}, computed as
+// 2 (a_r + a_{r+1}) + a_{r+1} + a_{r+2} + a_{r+3}.
+fn mix_columns(state: &mut State) {
+    let mut t = [0u64; 8];
+    for i in 0..8 {
+        t[i] = state[i] ^ rotate_rows(state[i], 1);
+    }
+    let t = xtime(&t);
+    for i in 0..8 {
+        let s = state[i];
+        state[i] = t[i] ^ rotate_rows(s, 1) ^ rotate_rows(s, 2) ^ rotate_rows(s, 3);
+    }
+}
+
+// InvMixColumns is MixColumns after adding 4 (a_r + a_{r+2}) to rows r and
+// r + 2.
+fn inv_mix_columns(state: &mut State) {
+    let mut t = [0u64; 8];
+    for i in 0..8 {
+        t[i] = state[i] ^ rotate_rows(state[i], 2);
+    }
+    let t = xtime(&xtime(&t));
+    for i in 0..8 {
+        state[i] ^= t[i];
+    }
+    mix_columns(state);
+}
+
+// The round keys in the same layout as the state, every block using the
+// same key.
+fn round_keys(nr: u8, round_key: &[u8]) -> [State; 15] {
+    let mut keys = [[0u64; 8]; 15];
+    for (state, key) in keys
+        .iter_mut()
+        .zip(round_key.chunks(16))
+        .take(nr as usize + 1)
+    {
+        let mut bytes = [0u8; 64];
+        for block in bytes.chunks_mut(16) {
+            block.copy_from_slice(key);
+        }
+        *state = pack(&bytes);
+    }
+    keys
+}
+
+fn add_round_key(state: &mut State, key: &State) {
+    for (s, k) in state.iter_mut().zip(key.iter()) {
+        *s ^= k;
+    }
+}
+
+fn encrypt_state(nr: usize, state: &mut State, keys: &[State]) {
+    add_round_key(state, &keys[0]);
+    for (round, key) in keys.iter().enumerate().skip(1) {
+        sub_bytes(state);
+        shift_rows(state, false);
+        if round != nr {
+            mix_columns(state);
+        }
+        add_round_key(state, key);
+    }
+}
+
+fn decrypt_state(nr: usize, state: &mut State, keys: &[State]) {
+    add_round_key(state, &keys[nr]);
+    for round in (0..nr).rev() {
+        shift_rows(state, true);
+        inv_sub_bytes(state);
+        add_round_key(state, &keys[round]);
+        if round != 0 {
+            inv_mix_columns(state);
+        }
+    }
+}
+
+// Runs `f` on every group of four blocks of `blocks`, whose length is a
+// multiple of 16. A last group with fewer blocks is padded with zeros.
+fn for_each_group<F: Fn(&mut State)>(blocks: &mut [u8], f: F) {
+    for chunk in blocks.chunks_mut(64) {
+        let mut bytes = [0u8; 64];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        let mut state = pack(&bytes);
+        f(&mut state);
+        unpack(&state, &mut bytes);
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+pub(crate) fn encrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    let keys = round_keys(nr, round_key);
+    let keys = &keys[..nr as usize + 1];
+    for_each_group(blocks, |state| encrypt_state(nr as usize, state, keys));
+}
+
+pub(crate) fn decrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    let keys = round_keys(nr, round_key);
+    let keys = &keys[..nr as usize + 1];
+    for_each_group(blocks, |state| decrypt_state(nr as usize, state, keys));
+}
+
+// SubWord of the key expansion.
+pub(crate) fn sub_word(word: &mut [u8; 4]) {
+    let mut bytes = [0u8; 64];
+    bytes[..4].copy_from_slice(word);
+    let mut state = pack(&bytes);
+    sub_bytes(&mut state);
+    unpack(&state, &mut bytes);
+    word.copy_from_slice(&bytes[..4]);
+}
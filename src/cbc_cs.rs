@@ -0,0 +1,186 @@
+//! CBC with ciphertext stealing, in the CBC-CS1, CBC-CS2 and CBC-CS3
+//! variants of the addendum to NIST SP 800-38A.
+
+use super::{cipher, inv_cipher, Error, Mode, AES};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    CS1,
+    CS2,
+    CS3,
+}
+
+fn xor(a: &mut [u8], b: &[u8]) {
+    for (x, y) in a.iter_mut().zip(b.iter()) {
+        *x ^= y;
+    }
+}
+
+// The variants only differ in the order of the last two ciphertext blocks.
+// CS1 keeps the partial block C_{n-1}* first, CS3 always swaps, and CS2
+// swaps only if the last block is partial.
+fn swapped(variant: Variant, d: usize) -> bool {
+    match variant {
+        Variant::CS1 => false,
+        Variant::CS2 => d != 16,
+        Variant::CS3 => true,
+    }
+}
+
+// Ciphertext stealing needs at least one full block.
+fn check_params(buf: &[u8]) -> Result<(), Error> {
+    if buf.len() < 16 {
+        return Err(Error::MisalignedBuffer);
+    }
+    Ok(())
+}
+
+// Both directions chain from `iv` and leave the full last ciphertext block
+// C_n in it, which is where CBC would continue from.
+fn encrypt(ctx: &AES, iv: &mut [u8; 16], buf: &mut [u8], variant: Variant) -> Result<(), Error> {
+    check_params(buf)?;
+
+    // n blocks, the last of them holding d bytes.
+    let n = buf.len().div_ceil(16);
+    let d = buf.len() - 16 * (n - 1);
+    let (head, tail) = buf.split_at_mut(16 * n.saturating_sub(2));
+
+    let mut prev = *iv;
+    for chunk in head.chunks_mut(16) {
+        xor(chunk, &prev);
+        cipher(ctx.nr(), chunk, &ctx.round_key);
+        prev.copy_from_slice(chunk);
+    }
+    if n == 1 {
+        xor(tail, &prev);
+        cipher(ctx.nr(), tail, &ctx.round_key);
+        iv.copy_from_slice(tail);
+        return Ok(());
+    }
+
+    // C_{n-1} = CIPH(P_{n-1} xor C_{n-2}), C_n = CIPH((P_n || 0*) xor C_{n-1})
+    let mut c_prev = [0u8; 16];
+    c_prev.copy_from_slice(&tail[..16]);
+    xor(&mut c_prev, &prev);
+    cipher(ctx.nr(), &mut c_prev, &ctx.round_key);
+
+    let mut c_last = c_prev;
+    xor(&mut c_last, &tail[16..]);
+    cipher(ctx.nr(), &mut c_last, &ctx.round_key);
+
+    if swapped(variant, d) {
+        tail[..16].copy_from_slice(&c_last);
+        tail[16..].copy_from_slice(&c_prev[..d]);
+    } else {
+        tail[..d].copy_from_slice(&c_prev[..d]);
+        tail[d..].copy_from_slice(&c_last);
+    }
+    *iv = c_last;
+    Ok(())
+}
+
+fn decrypt(ctx: &AES, iv: &mut [u8; 16], buf: &mut [u8], variant: Variant) -> Result<(), Error> {
+    check_params(buf)?;
+
+    let n = buf.len().div_ceil(16);
+    let d = buf.len() - 16 * (n - 1);
+    let (head, tail) = buf.split_at_mut(16 * n.saturating_sub(2));
+
+    let mut prev = *iv;
+    let mut next = [0u8; 16];
+    for chunk in head.chunks_mut(16) {
+        next.copy_from_slice(chunk);
+        inv_cipher(ctx.nr(), chunk, &ctx.round_key);
+        xor(chunk, &prev);
+        prev = next;
+    }
+    if n == 1 {
+        iv.copy_from_slice(tail);
+        inv_cipher(ctx.nr(), tail, &ctx.round_key);
+        xor(tail, &prev);
+        return Ok(());
+    }
+
+    let mut c_prev = [0u8; 16];
+    let mut c_last = [0u8; 16];
+    if swapped(variant, d) {
+        c_last.copy_from_slice(&tail[..16]);
+        c_prev[..d].copy_from_slice(&tail[16..]);
+    } else {
+        c_prev[..d].copy_from_slice(&tail[..d]);
+        c_last.copy_from_slice(&tail[d..]);
+    }
+    *iv = c_last;
+
+    // DECIPH(C_n) = (P_n || 0*) xor C_{n-1}, so its last 16 - d bytes are
+    // the bytes of C_{n-1} that were stolen.
+    let mut z = c_last;
+    inv_cipher(ctx.nr(), &mut z, &ctx.round_key);
+    c_prev[d..].copy_from_slice(&z[d..]);
+    xor(&mut z[..d], &c_prev[..d]);
+
+    inv_cipher(ctx.nr(), &mut c_prev, &ctx.round_key);
+    xor(&mut c_prev, &prev);
+
+    tail[..16].copy_from_slice(&c_prev);
+    tail[16..].copy_from_slice(&z[..d]);
+    Ok(())
+}
+
+// Runs one direction from `ctx.iv` and leaves C_n there, so the next call
+// continues the chain like CBC does.
+fn chain(ctx: &mut AES, buf: &mut [u8], variant: Variant, decrypting: bool) -> Result<(), Error> {
+    let mut iv = ctx.iv;
+    if decrypting {
+        decrypt(ctx, &mut iv, buf, variant)?;
+    } else {
+        encrypt(ctx, &mut iv, buf, variant)?;
+    }
+    ctx.iv = iv;
+    Ok(())
+}
+
+/// Encrypts `buf` in place with CBC-CS1. `buf` can have any length of at
+/// least 16 bytes, the ciphertext is as long as the plaintext. Shorter
+/// buffers are rejected with `Error::MisalignedBuffer`.
+///
+/// Like CBC, this continues from the IV in `ctx` and leaves the last full
+/// ciphertext block there for the next call.
+pub fn aes_cbc_cs1_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    chain(ctx, buf, Variant::CS1, false)
+}
+
+pub fn aes_cbc_cs1_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    chain(ctx, buf, Variant::CS1, true)
+}
+
+/// Encrypts `buf` in place with CBC-CS2, which is plain CBC if the length
+/// of `buf` is a multiple of 16.
+pub fn aes_cbc_cs2_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    chain(ctx, buf, Variant::CS2, false)
+}
+
+pub fn aes_cbc_cs2_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    chain(ctx, buf, Variant::CS2, true)
+}
+
+/// Encrypts `buf` in place with CBC-CS3, the variant used by Kerberos,
+/// which always swaps the last two blocks.
+pub fn aes_cbc_cs3_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    chain(ctx, buf, Variant::CS3, false)
+}
+
+pub fn aes_cbc_cs3_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    chain(ctx, buf, Variant::CS3, true)
+}
+
+// For `AES::encrypt` and `AES::decrypt`.
+pub(crate) fn xcrypt(ctx: &mut AES, buf: &mut [u8], decrypting: bool) -> Result<(), Error> {
+    let variant = match ctx.mode {
+        Mode::CBCCS1 => Variant::CS1,
+        Mode::CBCCS2 => Variant::CS2,
+        Mode::CBCCS3 => Variant::CS3,
+        _ => unreachable!(),
+    };
+    chain(ctx, buf, variant, decrypting)
+}
@@ -0,0 +1,157 @@
+//! Counter with CBC-MAC, as specified in NIST SP 800-38C and RFC 3610.
+
+use super::{cipher, constant_time_eq, Error, AES};
+
+// Running CBC-MAC over a stream of bytes.
+struct CbcMac<'a> {
+    ctx: &'a AES,
+    x: [u8; 16],
+    pos: usize,
+}
+
+impl<'a> CbcMac<'a> {
+    fn new(ctx: &'a AES) -> CbcMac<'a> {
+        CbcMac {
+            ctx,
+            x: [0u8; 16],
+            pos: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.x[self.pos] ^= b;
+            self.pos += 1;
+            if self.pos == 16 {
+                cipher(self.ctx.nr(), &mut self.x, &self.ctx.round_key);
+                self.pos = 0;
+            }
+        }
+    }
+
+    // Completes the current block with zeros.
+    fn pad(&mut self) {
+        if self.pos != 0 {
+            cipher(self.ctx.nr(), &mut self.x, &self.ctx.round_key);
+            self.pos = 0;
+        }
+    }
+}
+
+fn check_params(nonce: &[u8], text: &[u8], tag_len: usize) -> Result<(), Error> {
+    if !(7..=13).contains(&nonce.len()) {
+        return Err(Error::BadIvLength);
+    }
+    if !(4..=16).contains(&tag_len) || !tag_len.is_multiple_of(2) {
+        return Err(Error::BadTagLength);
+    }
+    // The message length has to fit into the remaining 15 - n bytes, which
+    // also hold the block counter.
+    let l = 15 - nonce.len();
+    if l < 8 && (text.len() as u64) >= (1 << (8 * l)) {
+        return Err(Error::MessageTooLong);
+    }
+    Ok(())
+}
+
+// Builds the counter block A_i, which also carries the nonce.
+fn counter_block(nonce: &[u8], i: u64) -> [u8; 16] {
+    let l = 15 - nonce.len();
+    let mut block = [0u8; 16];
+    block[0] = (l - 1) as u8;
+    block[1..=nonce.len()].copy_from_slice(nonce);
+    block[16 - l..].copy_from_slice(&i.to_be_bytes()[8 - l..]);
+    block
+}
+
+// Computes the CBC-MAC over B_0, the encoded associated data and the
+// plaintext, and encrypts it with S_0.
+fn compute_tag(ctx: &AES, nonce: &[u8], aad: &[u8], plain: &[u8], tag_len: usize) -> [u8; 16] {
+    let mut mac = CbcMac::new(ctx);
+
+    let mut b0 = counter_block(nonce, plain.len() as u64);
+    b0[0] |= (((tag_len - 2) / 2) << 3) as u8;
+    if !aad.is_empty() {
+        b0[0] |= 0x40;
+    }
+    mac.update(&b0);
+
+    if !aad.is_empty() {
+        let len = aad.len() as u64;
+        if len < 0xff00 {
+            mac.update(&(len as u16).to_be_bytes());
+        } else if len <= 0xffff_ffff {
+            mac.update(&[0xff, 0xfe]);
+            mac.update(&(len as u32).to_be_bytes());
+        } else {
+            mac.update(&[0xff, 0xff]);
+            mac.update(&len.to_be_bytes());
+        }
+        mac.update(aad);
+        mac.pad();
+    }
+
+    mac.update(plain);
+    mac.pad();
+
+    let mut s0 = counter_block(nonce, 0);
+    cipher(ctx.nr(), &mut s0, &ctx.round_key);
+    let mut tag = mac.x;
+    for (t, s) in tag.iter_mut().zip(s0.iter()) {
+        *t ^= s;
+    }
+    tag
+}
+
+// Encrypts or decrypts the payload with the counter blocks A_1, A_2, ...
+fn ctr(ctx: &AES, nonce: &[u8], buf: &mut [u8]) {
+    for (i, chunk) in buf.chunks_mut(16).enumerate() {
+        let mut keystream = counter_block(nonce, i as u64 + 1);
+        cipher(ctx.nr(), &mut keystream, &ctx.round_key);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= k;
+        }
+    }
+}
+
+/// Encrypts `buf` in place and writes the authentication tag into `tag`.
+///
+/// The nonce length selects the size of the length field, the tag length
+/// is taken from `tag`.
+pub fn aes_ccm_seal(
+    ctx: &AES,
+    nonce: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error> {
+    check_params(nonce, buf, tag.len())?;
+
+    let full_tag = compute_tag(ctx, nonce, aad, buf, tag.len());
+    ctr(ctx, nonce, buf);
+    let tag_len = tag.len();
+    tag.copy_from_slice(&full_tag[..tag_len]);
+    Ok(())
+}
+
+/// Decrypts `buf` in place and verifies `tag`.
+///
+/// On a tag mismatch `buf` is restored to the ciphertext, so no unverified
+/// plaintext is released, and `Error::Authentication` is returned.
+pub fn aes_ccm_open(
+    ctx: &AES,
+    nonce: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error> {
+    check_params(nonce, buf, tag.len())?;
+
+    ctr(ctx, nonce, buf);
+    let full_tag = compute_tag(ctx, nonce, aad, buf, tag.len());
+    if !constant_time_eq(&full_tag[..tag.len()], tag) {
+        ctr(ctx, nonce, buf);
+        return Err(Error::Authentication);
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! CMAC message authentication, as specified in NIST SP 800-38B and RFC 4493.
+
+use super::{cipher, constant_time_eq, Error, AES};
+
+// Doubling in GF(2^128): shifts the block left by one bit and reduces with
+// R_128 = 0x87 if a bit was shifted out. The reduction is masked, so the
+// running time does not depend on the block.
+pub(crate) fn dbl(block: &[u8; 16]) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    for i in 0..15 {
+        out[i] = (block[i] << 1) | (block[i + 1] >> 7);
+    }
+    out[15] = (block[15] << 1) ^ (0x87 & 0u8.wrapping_sub(block[0] >> 7));
+    out
+}
+
+/// Incremental CMAC computation under the key of an `AES` context.
+pub struct CMAC<'a> {
+    ctx: &'a AES,
+    k1: [u8; 16],
+    k2: [u8; 16],
+    // CBC-MAC state over all complete blocks that have been processed.
+    x: [u8; 16],
+    // The last block is held back until it is known whether more data
+    // follows, since it is mixed with a subkey before encryption.
+    block: [u8; 16],
+    pos: usize,
+}
+
+impl<'a> CMAC<'a> {
+    pub fn new(ctx: &'a AES) -> CMAC<'a> {
+        // Subkey generation, L = CIPH_K(0^128).
+        let mut l = [0u8; 16];
+        cipher(ctx.nr(), &mut l, &ctx.round_key);
+        let k1 = dbl(&l);
+        let k2 = dbl(&k1);
+
+        CMAC {
+            ctx,
+            k1,
+            k2,
+            x: [0u8; 16],
+            block: [0u8; 16],
+            pos: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for b in data {
+            if self.pos == 16 {
+                for (x, m) in self.x.iter_mut().zip(self.block.iter()) {
+                    *x ^= m;
+                }
+                cipher(self.ctx.nr(), &mut self.x, &self.ctx.round_key);
+                self.pos = 0;
+            }
+            self.block[self.pos] = *b;
+            self.pos += 1;
+        }
+    }
+
+    /// Returns the full 16 byte tag.
+    pub fn finalize(mut self) -> [u8; 16] {
+        let subkey = if self.pos == 16 {
+            self.k1
+        } else {
+            // Pad the incomplete block with a single one bit and zeros.
+            self.block[self.pos] = 0x80;
+            for b in self.block[self.pos + 1..].iter_mut() {
+                *b = 0;
+            }
+            self.k2
+        };
+
+        for ((x, m), k) in self.x.iter_mut().zip(self.block.iter()).zip(subkey.iter()) {
+            *x ^= m ^ k;
+        }
+        cipher(self.ctx.nr(), &mut self.x, &self.ctx.round_key);
+        self.x
+    }
+
+    /// Compares the tag in constant time. `tag` may be truncated, but not
+    /// empty or longer than 16 bytes, which is `Error::BadTagLength`.
+    pub fn verify(self, tag: &[u8]) -> Result<(), Error> {
+        if tag.is_empty() || tag.len() > 16 {
+            return Err(Error::BadTagLength);
+        }
+        let full_tag = self.finalize();
+        if constant_time_eq(&full_tag[..tag.len()], tag) {
+            Ok(())
+        } else {
+            Err(Error::Authentication)
+        }
+    }
+}
+
+/// Computes the CMAC of `msg` in one go.
+pub fn aes_cmac(ctx: &AES, msg: &[u8]) -> [u8; 16] {
+    let mut mac = CMAC::new(ctx);
+    mac.update(msg);
+    mac.finalize()
+}
+
+/// Checks `tag`, which may be truncated, against the CMAC of `msg`.
+pub fn aes_cmac_verify(ctx: &AES, msg: &[u8], tag: &[u8]) -> Result<(), Error> {
+    let mut mac = CMAC::new(ctx);
+    mac.update(msg);
+    mac.verify(tag)
+}
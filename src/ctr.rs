@@ -0,0 +1,249 @@
+//! Counter block layouts for the CTR mode of NIST SP 800-38A.
+
+use super::{cipher, cipher_blocks, xor_with_iv, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// The part of the 16-byte counter block that is incremented for every
+/// block, the rest of it being a fixed nonce.
+///
+/// Incrementing past the largest counter value returns
+/// `Error::CounterExhausted` instead of wrapping around to keystream that
+/// was already used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterLayout {
+    position: usize,
+    width: usize,
+    endian: Endian,
+}
+
+impl CounterLayout {
+    /// The whole block is a 128-bit big-endian counter, as in SP 800-38A.
+    pub const FULL: CounterLayout = CounterLayout {
+        position: 0,
+        width: 16,
+        endian: Endian::Big,
+    };
+
+    /// A 96-bit nonce followed by a 32-bit big-endian counter, as in GCM
+    /// and in the nonce || IV || counter blocks of RFC 3686.
+    pub const NONCE_96: CounterLayout = CounterLayout {
+        position: 12,
+        width: 4,
+        endian: Endian::Big,
+    };
+
+    /// A counter of `width` bytes starting at byte `position` of the block.
+    /// Returns `Error::BadCounterLayout` unless it has 1 to 16 bytes and
+    /// fits into the block.
+    pub fn new(position: usize, width: usize, endian: Endian) -> Result<CounterLayout, Error> {
+        if width == 0 || width > 16 || position > 16 - width {
+            return Err(Error::BadCounterLayout);
+        }
+        Ok(CounterLayout {
+            position,
+            width,
+            endian,
+        })
+    }
+
+    fn max(&self) -> u128 {
+        u128::MAX >> (128 - 8 * self.width)
+    }
+
+    fn get(&self, block: &[u8; 16]) -> u128 {
+        let mut bytes = [0u8; 16];
+        let counter = &block[self.position..self.position + self.width];
+        match self.endian {
+            Endian::Big => {
+                bytes[16 - self.width..].copy_from_slice(counter);
+                u128::from_be_bytes(bytes)
+            }
+            Endian::Little => {
+                bytes[..self.width].copy_from_slice(counter);
+                u128::from_le_bytes(bytes)
+            }
+        }
+    }
+
+    fn set(&self, block: &mut [u8; 16], value: u128) {
+        let counter = &mut block[self.position..self.position + self.width];
+        match self.endian {
+            Endian::Big => counter.copy_from_slice(&value.to_be_bytes()[16 - self.width..]),
+            Endian::Little => counter.copy_from_slice(&value.to_le_bytes()[..self.width]),
+        }
+    }
+}
+
+/// Builds the initial counter block of RFC 3686, nonce || IV || 1, to be
+/// used with `CounterLayout::NONCE_96`.
+pub fn rfc3686_counter_block(nonce: &[u8; 4], iv: &[u8; 8]) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block[..4].copy_from_slice(nonce);
+    block[4..12].copy_from_slice(iv);
+    block[15] = 1;
+    block
+}
+
+// The state of a CTR stream besides the counter block itself. `offset`
+// bytes of `keystream` have been used, 0 meaning the next byte needs a new
+// block. `exhausted` is set once the largest counter value has been used.
+// `last` is the counter block as this state left it, so a counter block
+// that was changed from outside starts a new stream.
+#[derive(Clone, Copy)]
+pub(crate) struct CtrState {
+    layout: CounterLayout,
+    start: [u8; 16],
+    last: [u8; 16],
+    keystream: [u8; 16],
+    offset: usize,
+    exhausted: bool,
+}
+
+impl CtrState {
+    pub(crate) fn new(layout: CounterLayout, counter: &[u8; 16]) -> CtrState {
+        CtrState {
+            layout,
+            start: *counter,
+            last: *counter,
+            keystream: [0u8; 16],
+            offset: 0,
+            exhausted: false,
+        }
+    }
+
+    pub(crate) fn layout(&self) -> CounterLayout {
+        self.layout
+    }
+
+    // Starts a new stream if `counter` was replaced by a different value
+    // since the last call. Equal values can't be told apart from the stream
+    // continuing, so restarting there needs `AES::set_iv`.
+    fn sync(&mut self, counter: &[u8; 16]) {
+        if *counter != self.last {
+            *self = CtrState::new(self.layout, counter);
+        }
+    }
+
+    fn next_block(&mut self, nr: u8, round_key: &[u8], counter: &mut [u8; 16]) {
+        self.keystream = *counter;
+        cipher(nr, &mut self.keystream, round_key);
+        self.increment(counter);
+    }
+
+    fn increment(&mut self, counter: &mut [u8; 16]) {
+        let value = self.layout.get(counter);
+        if value == self.layout.max() {
+            self.exhausted = true;
+            self.layout.set(counter, 0);
+        } else {
+            self.layout.set(counter, value + 1);
+        }
+    }
+
+    // Checks that all counter blocks `buf` needs are left before touching
+    // it, so `buf` is unchanged on errors.
+    pub(crate) fn xcrypt(
+        &mut self,
+        nr: u8,
+        round_key: &[u8],
+        counter: &mut [u8; 16],
+        buf: &mut [u8],
+    ) -> Result<(), Error> {
+        self.sync(counter);
+        let left = if self.offset == 0 {
+            0
+        } else {
+            16 - self.offset
+        };
+        let blocks = buf.len().saturating_sub(left).div_ceil(16) as u128;
+        if blocks > 0 {
+            let last = self.layout.get(counter).checked_add(blocks - 1);
+            if self.exhausted || last.is_none_or(|last| last > self.layout.max()) {
+                return Err(Error::CounterExhausted);
+            }
+        }
+
+        // Use up the current keystream block, then encrypt the counter
+        // blocks for up to 16 whole blocks at a time, which the backends
+        // can process in parallel.
+        let (head, rest) = buf.split_at_mut(left.min(buf.len()));
+        let full = rest.len() - rest.len() % 16;
+        let (body, tail) = rest.split_at_mut(full);
+        self.xcrypt_bytes(nr, round_key, counter, head);
+
+        let mut keystream = [0u8; 256];
+        for chunk in body.chunks_mut(256) {
+            // The check above guarantees that none of these overflow.
+            let keystream = &mut keystream[..chunk.len()];
+            let value = self.layout.get(counter);
+            for (i, block) in keystream.chunks_mut(16).enumerate() {
+                let mut next = *counter;
+                self.layout.set(&mut next, value + i as u128);
+                block.copy_from_slice(&next);
+            }
+            self.layout
+                .set(counter, value + (chunk.len() / 16 - 1) as u128);
+            self.increment(counter);
+            cipher_blocks(nr, keystream, round_key);
+            for (block, k) in chunk.chunks_mut(16).zip(keystream.chunks(16)) {
+                xor_with_iv(block, k);
+            }
+        }
+
+        self.xcrypt_bytes(nr, round_key, counter, tail);
+        self.last = *counter;
+        Ok(())
+    }
+
+    fn xcrypt_bytes(&mut self, nr: u8, round_key: &[u8], counter: &mut [u8; 16], buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            if self.offset == 0 {
+                self.next_block(nr, round_key, counter);
+            }
+            *b ^= self.keystream[self.offset];
+            self.offset = (self.offset + 1) % 16;
+        }
+    }
+
+    // Sets `counter` to continue at `pos` bytes past the initial counter
+    // block, computing the keystream block if `pos` is in the middle of one.
+    // `pos` may be the end of the keystream, after which only empty buffers
+    // can be processed.
+    pub(crate) fn seek(
+        &mut self,
+        nr: u8,
+        round_key: &[u8],
+        counter: &mut [u8; 16],
+        pos: u64,
+    ) -> Result<(), Error> {
+        self.sync(counter);
+        let start = self.layout.get(&self.start);
+        let (blocks, offset) = ((pos / 16) as u128, (pos % 16) as usize);
+        // The blocks left after the initial one.
+        let room = self.layout.max() - start;
+        let end = blocks > 0 && blocks - 1 == room && offset == 0;
+        if blocks > room && !end {
+            return Err(Error::CounterExhausted);
+        }
+
+        *counter = self.start;
+        self.offset = offset;
+        self.exhausted = end;
+        if end {
+            // Where incrementing past the largest value leaves it.
+            self.layout.set(counter, 0);
+        } else {
+            self.layout.set(counter, start + blocks);
+        }
+        if self.offset != 0 {
+            self.next_block(nr, round_key, counter);
+        }
+        self.last = *counter;
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! EAX authenticated encryption, as described by Bellare, Rogaway and
+//! Wagner in "The EAX Mode of Operation".
+
+use super::cmac::CMAC;
+use super::{cipher, constant_time_eq, Error, AES};
+
+// OMAC^t_K(M): the CMAC of M, prefixed with a block holding the tweak t.
+fn omac(ctx: &AES, t: u8, data: &[u8]) -> [u8; 16] {
+    let mut prefix = [0u8; 16];
+    prefix[15] = t;
+    let mut mac = CMAC::new(ctx);
+    mac.update(&prefix);
+    mac.update(data);
+    mac.finalize()
+}
+
+// CTR mode starting at N', with the whole block as big endian counter.
+fn ctr(ctx: &AES, n: &[u8; 16], buf: &mut [u8]) {
+    let mut counter = u128::from_be_bytes(*n);
+    for chunk in buf.chunks_mut(16) {
+        let mut keystream = counter.to_be_bytes();
+        cipher(ctx.nr(), &mut keystream, &ctx.round_key);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= k;
+        }
+        counter = counter.wrapping_add(1);
+    }
+}
+
+// Tag = N' xor C' xor H'
+fn compute_tag(ctx: &AES, n: &[u8; 16], header: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+    let h = omac(ctx, 1, header);
+    let c = omac(ctx, 2, ciphertext);
+    let mut tag = *n;
+    for (t, (x, y)) in tag.iter_mut().zip(h.iter().zip(c.iter())) {
+        *t ^= x ^ y;
+    }
+    tag
+}
+
+fn check_params(tag_len: usize) -> Result<(), Error> {
+    if !(1..=16).contains(&tag_len) {
+        return Err(Error::BadTagLength);
+    }
+    Ok(())
+}
+
+/// Encrypts `buf` in place and writes the authentication tag into `tag`.
+///
+/// The nonce and header can have any length, the tag is truncated to the
+/// length of `tag`.
+pub fn aes_eax_seal(
+    ctx: &AES,
+    nonce: &[u8],
+    header: &[u8],
+    buf: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error> {
+    check_params(tag.len())?;
+
+    let n = omac(ctx, 0, nonce);
+    ctr(ctx, &n, buf);
+    let full_tag = compute_tag(ctx, &n, header, buf);
+    let tag_len = tag.len();
+    tag.copy_from_slice(&full_tag[..tag_len]);
+    Ok(())
+}
+
+/// Verifies `tag` and decrypts `buf` in place.
+///
+/// On a tag mismatch `buf` is left untouched and `Error::Authentication` is
+/// returned.
+pub fn aes_eax_open(
+    ctx: &AES,
+    nonce: &[u8],
+    header: &[u8],
+    buf: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error> {
+    check_params(tag.len())?;
+
+    let n = omac(ctx, 0, nonce);
+    let full_tag = compute_tag(ctx, &n, header, buf);
+    if !constant_time_eq(&full_tag[..tag.len()], tag) {
+        return Err(Error::Authentication);
+    }
+    ctr(ctx, &n, buf);
+    Ok(())
+}
@@ -0,0 +1,187 @@
+//! Galois/Counter Mode, as specified in NIST SP 800-38D.
+
+#[cfg(target_arch = "x86_64")]
+use super::aesni;
+use super::{cipher, constant_time_eq, Backend, Error, AES};
+
+// The reduction polynomial x^128 + x^7 + x^2 + x + 1 in the reflected
+// bit order used by GCM.
+pub(crate) const R: u128 = 0xe1 << 120;
+
+// Multiplication in GF(2^128), Algorithm 1 of SP 800-38D.
+// Blocks are read as big endian integers, so the leftmost bit of a block is
+// the most significant bit. Masks are used instead of branches, so the
+// running time does not depend on the operands.
+pub(crate) fn gf_mul(x: u128, y: u128) -> u128 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let backend = Backend::selected();
+        let hardware = backend == Backend::AesNi || backend == Backend::Vaes;
+        if hardware && is_x86_feature_detected!("pclmulqdq") {
+            return aesni::gf_mul(x, y);
+        }
+    }
+
+    let mut z = 0u128;
+    let mut v = y;
+    for i in (0..128).rev() {
+        z ^= v & 0u128.wrapping_sub((x >> i) & 1);
+        v = (v >> 1) ^ (R & 0u128.wrapping_sub(v & 1));
+    }
+    z
+}
+
+// The GHASH function, keyed with the hash subkey H.
+pub(crate) struct GHash {
+    h: u128,
+    y: u128,
+}
+
+impl GHash {
+    pub(crate) fn new(h: &[u8; 16]) -> GHash {
+        GHash {
+            h: u128::from_be_bytes(*h),
+            y: 0,
+        }
+    }
+
+    // Absorbs `data`, padding the last partial block with zeros.
+    pub(crate) fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.y = gf_mul(self.y ^ u128::from_be_bytes(block), self.h);
+        }
+    }
+
+    // Absorbs the length block [len(A)]64 || [len(C)]64, lengths in bits.
+    pub(crate) fn update_lengths(&mut self, aad_len: usize, text_len: usize) {
+        let block = ((aad_len as u128 * 8) << 64) | (text_len as u128 * 8);
+        self.y = gf_mul(self.y ^ block, self.h);
+    }
+
+    pub(crate) fn finalize(&self) -> [u8; 16] {
+        self.y.to_be_bytes()
+    }
+}
+
+// Increments the rightmost 32 bits of the block modulo 2^32.
+fn inc32(block: &mut [u8; 16]) {
+    let mut ctr = [0u8; 4];
+    ctr.copy_from_slice(&block[12..]);
+    let ctr = u32::from_be_bytes(ctr).wrapping_add(1);
+    block[12..].copy_from_slice(&ctr.to_be_bytes());
+}
+
+fn check_params(iv: &[u8], aad: &[u8], text: &[u8], tag_len: usize) -> Result<(), Error> {
+    if iv.is_empty() {
+        return Err(Error::BadIvLength);
+    }
+    if !(tag_len == 4 || tag_len == 8 || (12..=16).contains(&tag_len)) {
+        return Err(Error::BadTagLength);
+    }
+    // len(P) <= 2^39 - 256 bits, the limit of the 32 bit counter, and
+    // len(A) <= 2^64 - 1 bits.
+    if (text.len() as u64) > (1 << 36) - 32 {
+        return Err(Error::CounterExhausted);
+    }
+    if (aad.len() as u64) >= (1 << 61) {
+        return Err(Error::MessageTooLong);
+    }
+    Ok(())
+}
+
+// Derives the hash subkey H and the pre-counter block J0.
+fn setup(ctx: &AES, iv: &[u8]) -> (GHash, [u8; 16]) {
+    let mut h = [0u8; 16];
+    cipher(ctx.nr(), &mut h, &ctx.round_key);
+
+    let mut j0 = [0u8; 16];
+    if iv.len() == 12 {
+        j0[..12].copy_from_slice(iv);
+        j0[15] = 1;
+    } else {
+        let mut ghash = GHash::new(&h);
+        ghash.update_padded(iv);
+        ghash.update_lengths(0, iv.len());
+        j0 = ghash.finalize();
+    }
+
+    (GHash::new(&h), j0)
+}
+
+// The GCTR function, starting with the counter block after J0.
+fn gctr(ctx: &AES, j0: &[u8; 16], buf: &mut [u8]) {
+    let mut counter = *j0;
+    for chunk in buf.chunks_mut(16) {
+        inc32(&mut counter);
+        let mut keystream = counter;
+        cipher(ctx.nr(), &mut keystream, &ctx.round_key);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= k;
+        }
+    }
+}
+
+// Computes the full 16 byte tag over the associated data and ciphertext.
+fn compute_tag(
+    ctx: &AES,
+    mut ghash: GHash,
+    j0: &[u8; 16],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> [u8; 16] {
+    ghash.update_padded(aad);
+    ghash.update_padded(ciphertext);
+    ghash.update_lengths(aad.len(), ciphertext.len());
+
+    let mut tag = *j0;
+    cipher(ctx.nr(), &mut tag, &ctx.round_key);
+    for (t, s) in tag.iter_mut().zip(ghash.finalize().iter()) {
+        *t ^= s;
+    }
+    tag
+}
+
+/// Encrypts `buf` in place and writes the authentication tag into `tag`.
+///
+/// The tag is truncated to the length of `tag`. A 12 byte `iv` is used
+/// directly, IVs of any other length are hashed with GHASH first.
+pub fn aes_gcm_seal(
+    ctx: &AES,
+    iv: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error> {
+    check_params(iv, aad, buf, tag.len())?;
+    let (ghash, j0) = setup(ctx, iv);
+
+    gctr(ctx, &j0, buf);
+    let full_tag = compute_tag(ctx, ghash, &j0, aad, buf);
+    let tag_len = tag.len();
+    tag.copy_from_slice(&full_tag[..tag_len]);
+    Ok(())
+}
+
+/// Verifies `tag` and decrypts `buf` in place.
+///
+/// On a tag mismatch `buf` is left untouched and `Error::Authentication` is
+/// returned.
+pub fn aes_gcm_open(
+    ctx: &AES,
+    iv: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error> {
+    check_params(iv, aad, buf, tag.len())?;
+    let (ghash, j0) = setup(ctx, iv);
+
+    let full_tag = compute_tag(ctx, ghash, &j0, aad, buf);
+    if !constant_time_eq(&full_tag[..tag.len()], tag) {
+        return Err(Error::Authentication);
+    }
+    gctr(ctx, &j0, buf);
+    Ok(())
+}
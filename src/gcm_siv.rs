@@ -0,0 +1,162 @@
+//! AES-GCM-SIV nonce misuse-resistant authenticated encryption, as
+//! specified in RFC 8452.
+
+use super::gcm::{gf_mul, R};
+use super::{cipher, constant_time_eq, Error, Mode, Size, AES};
+
+// POLYVAL works in the same field as GHASH, with the bytes of each block
+// reversed. Following RFC 8452 Appendix A, it is computed with the GHASH
+// multiplication: the key is byte reversed and multiplied by x, and the
+// blocks are read little endian.
+struct Polyval {
+    h: u128,
+    s: u128,
+}
+
+impl Polyval {
+    fn new(key: &[u8]) -> Polyval {
+        let mut h = [0u8; 16];
+        h.copy_from_slice(key);
+        let h = u128::from_le_bytes(h);
+        // mulX_GHASH
+        let h = (h >> 1) ^ (R & 0u128.wrapping_sub(h & 1));
+        Polyval { h, s: 0 }
+    }
+
+    fn update_block(&mut self, block: [u8; 16]) {
+        self.s = gf_mul(self.s ^ u128::from_le_bytes(block), self.h);
+    }
+
+    // Absorbs `data`, padding the last partial block with zeros.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0u8; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(block);
+        }
+    }
+
+    fn finalize(&self) -> [u8; 16] {
+        self.s.to_le_bytes()
+    }
+}
+
+fn check_params(ctx: &AES, aad: &[u8], text: &[u8]) -> Result<(), Error> {
+    // AES-GCM-SIV is only defined for 128 and 256 bit keys.
+    if ctx.size == Size::AES192 {
+        return Err(Error::BadKeyLength);
+    }
+    if (text.len() as u64) > 1 << 36 {
+        return Err(Error::CounterExhausted);
+    }
+    if (aad.len() as u64) > 1 << 36 {
+        return Err(Error::MessageTooLong);
+    }
+    Ok(())
+}
+
+// Derives the per-nonce message authentication and encryption keys from
+// the key-generating key in `ctx`.
+fn derive_keys(ctx: &AES, nonce: &[u8; 12]) -> ([u8; 16], AES) {
+    let blocks = if ctx.size == Size::AES256 { 6 } else { 4 };
+    let mut keys = [0u8; 48];
+    for i in 0..blocks {
+        let mut block = [0u8; 16];
+        block[..4].copy_from_slice(&(i as u32).to_le_bytes());
+        block[4..].copy_from_slice(nonce);
+        cipher(ctx.nr(), &mut block, &ctx.round_key);
+        keys[i * 8..i * 8 + 8].copy_from_slice(&block[..8]);
+    }
+
+    let mut auth_key = [0u8; 16];
+    auth_key.copy_from_slice(&keys[..16]);
+    let enc_key = AES::new(ctx.size, Mode::GCMSIV, &keys[16..blocks * 8], &[0u8; 16]);
+    (auth_key, enc_key)
+}
+
+fn compute_tag(
+    auth_key: &[u8; 16],
+    enc_key: &AES,
+    nonce: &[u8; 12],
+    aad: &[u8],
+    plain: &[u8],
+) -> [u8; 16] {
+    let mut polyval = Polyval::new(auth_key);
+    polyval.update_padded(aad);
+    polyval.update_padded(plain);
+    let mut lengths = [0u8; 16];
+    lengths[..8].copy_from_slice(&(aad.len() as u64 * 8).to_le_bytes());
+    lengths[8..].copy_from_slice(&(plain.len() as u64 * 8).to_le_bytes());
+    polyval.update_block(lengths);
+
+    let mut tag = polyval.finalize();
+    for (t, n) in tag.iter_mut().zip(nonce.iter()) {
+        *t ^= n;
+    }
+    tag[15] &= 0x7f;
+    cipher(enc_key.nr(), &mut tag, &enc_key.round_key);
+    tag
+}
+
+// CTR mode with the tag as initial counter block. Only the first 32 bits
+// are a little endian counter, which wraps without carrying into the rest.
+fn ctr(enc_key: &AES, tag: &[u8; 16], buf: &mut [u8]) {
+    let mut counter = *tag;
+    counter[15] |= 0x80;
+
+    for chunk in buf.chunks_mut(16) {
+        let mut keystream = counter;
+        cipher(enc_key.nr(), &mut keystream, &enc_key.round_key);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= k;
+        }
+
+        let mut ctr = [0u8; 4];
+        ctr.copy_from_slice(&counter[..4]);
+        let ctr = u32::from_le_bytes(ctr).wrapping_add(1);
+        counter[..4].copy_from_slice(&ctr.to_le_bytes());
+    }
+}
+
+/// Encrypts `buf` in place and writes the authentication tag into `tag`.
+///
+/// `ctx` holds the key-generating key, which must be an AES-128 or AES-256
+/// key, other keys are rejected with `Error::BadKeyLength`. The encryption
+/// keys are derived from it for every nonce.
+pub fn aes_gcm_siv_seal(
+    ctx: &AES,
+    nonce: &[u8; 12],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &mut [u8; 16],
+) -> Result<(), Error> {
+    check_params(ctx, aad, buf)?;
+    let (auth_key, enc_key) = derive_keys(ctx, nonce);
+
+    *tag = compute_tag(&auth_key, &enc_key, nonce, aad, buf);
+    ctr(&enc_key, tag, buf);
+    Ok(())
+}
+
+/// Decrypts `buf` in place and verifies `tag`.
+///
+/// On a tag mismatch `buf` is restored to the ciphertext, so no unverified
+/// plaintext is released, and `Error::Authentication` is returned.
+pub fn aes_gcm_siv_open(
+    ctx: &AES,
+    nonce: &[u8; 12],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &[u8; 16],
+) -> Result<(), Error> {
+    check_params(ctx, aad, buf)?;
+    let (auth_key, enc_key) = derive_keys(ctx, nonce);
+
+    ctr(&enc_key, tag, buf);
+    let expected = compute_tag(&auth_key, &enc_key, nonce, aad, buf);
+    if !constant_time_eq(&expected, tag) {
+        ctr(&enc_key, tag, buf);
+        return Err(Error::Authentication);
+    }
+    Ok(())
+}
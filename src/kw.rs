@@ -0,0 +1,162 @@
+//! AES key wrap (KW) and key wrap with padding (KWP), as specified in
+//! NIST SP 800-38F, RFC 3394 and RFC 5649.
+
+use super::{cipher, constant_time_eq, inv_cipher, Error, AES};
+
+// Default initial value of KW.
+const ICV1: [u8; 8] = [0xa6; 8];
+// Constant half of the alternative initial value of KWP, followed by the
+// 32 bit length of the key.
+const ICV2: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+// The wrapping function W: 6n steps over the n semiblocks in r, chaining
+// through the integrity register a.
+fn wrap_semiblocks(kek: &AES, a: &mut [u8; 8], r: &mut [u8]) {
+    let n = r.len() / 8;
+    let mut b = [0u8; 16];
+    for j in 0..6 {
+        for (i, semiblock) in r.chunks_mut(8).enumerate() {
+            b[..8].copy_from_slice(a);
+            b[8..].copy_from_slice(semiblock);
+            cipher(kek.nr(), &mut b, &kek.round_key);
+
+            let t = ((n * j + i + 1) as u64).to_be_bytes();
+            for (x, (y, z)) in a.iter_mut().zip(b[..8].iter().zip(t.iter())) {
+                *x = y ^ z;
+            }
+            semiblock.copy_from_slice(&b[8..]);
+        }
+    }
+}
+
+// The unwrapping function W^-1, running the steps of W backwards.
+fn unwrap_semiblocks(kek: &AES, a: &mut [u8; 8], r: &mut [u8]) {
+    let n = r.len() / 8;
+    let mut b = [0u8; 16];
+    for j in (0..6).rev() {
+        for (i, semiblock) in r.chunks_mut(8).enumerate().rev() {
+            let t = ((n * j + i + 1) as u64).to_be_bytes();
+            for (x, (y, z)) in b[..8].iter_mut().zip(a.iter().zip(t.iter())) {
+                *x = y ^ z;
+            }
+            b[8..].copy_from_slice(semiblock);
+            inv_cipher(kek.nr(), &mut b, &kek.round_key);
+
+            a.copy_from_slice(&b[..8]);
+            semiblock.copy_from_slice(&b[8..]);
+        }
+    }
+}
+
+/// Wraps `key` under the key-encryption key `kek` with KW.
+///
+/// The key must be a multiple of 8 bytes and at least 16 bytes long,
+/// otherwise `Error::MisalignedBuffer` is returned. The result is 8 bytes
+/// longer than the key.
+pub fn aes_kw_wrap(kek: &AES, key: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.len() < 16 || !key.len().is_multiple_of(8) {
+        return Err(Error::MisalignedBuffer);
+    }
+
+    let mut a = ICV1;
+    let mut out = vec![0u8; key.len() + 8];
+    out[8..].copy_from_slice(key);
+    wrap_semiblocks(kek, &mut a, &mut out[8..]);
+    out[..8].copy_from_slice(&a);
+    Ok(out)
+}
+
+/// Unwraps a key wrapped with `aes_kw_wrap`.
+///
+/// Returns `Error::Authentication` if the integrity check fails.
+pub fn aes_kw_unwrap(kek: &AES, wrapped: &[u8]) -> Result<Vec<u8>, Error> {
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(Error::Authentication);
+    }
+
+    let mut a = [0u8; 8];
+    a.copy_from_slice(&wrapped[..8]);
+    let mut key = wrapped[8..].to_vec();
+    unwrap_semiblocks(kek, &mut a, &mut key);
+
+    if !constant_time_eq(&a, &ICV1) {
+        return Err(Error::Authentication);
+    }
+    Ok(key)
+}
+
+/// Wraps `key` of any length between 1 and 2^32 - 1 bytes with KWP.
+///
+/// The key is padded with zeros to a multiple of 8 bytes, the result is
+/// 8 bytes longer than the padded key. Empty keys are rejected with
+/// `Error::MisalignedBuffer`, longer ones with `Error::MessageTooLong`.
+pub fn aes_kwp_wrap(kek: &AES, key: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.is_empty() {
+        return Err(Error::MisalignedBuffer);
+    }
+    if (key.len() as u64) >= (1 << 32) {
+        return Err(Error::MessageTooLong);
+    }
+
+    let mut a = [0u8; 8];
+    a[..4].copy_from_slice(&ICV2);
+    a[4..].copy_from_slice(&(key.len() as u32).to_be_bytes());
+
+    let padded_len = key.len().div_ceil(8) * 8;
+    let mut out = vec![0u8; padded_len + 8];
+    out[8..8 + key.len()].copy_from_slice(key);
+
+    if padded_len == 8 {
+        // A single semiblock is encrypted together with the initial value.
+        out[..8].copy_from_slice(&a);
+        cipher(kek.nr(), &mut out, &kek.round_key);
+    } else {
+        wrap_semiblocks(kek, &mut a, &mut out[8..]);
+        out[..8].copy_from_slice(&a);
+    }
+    Ok(out)
+}
+
+/// Unwraps a key wrapped with `aes_kwp_wrap` and strips the padding.
+///
+/// Returns `Error::Authentication` if the integrity check fails.
+pub fn aes_kwp_unwrap(kek: &AES, wrapped: &[u8]) -> Result<Vec<u8>, Error> {
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(Error::Authentication);
+    }
+
+    let mut a = [0u8; 8];
+    let mut key;
+    if wrapped.len() == 16 {
+        let mut b = [0u8; 16];
+        b.copy_from_slice(wrapped);
+        inv_cipher(kek.nr(), &mut b, &kek.round_key);
+        a.copy_from_slice(&b[..8]);
+        key = b[8..].to_vec();
+    } else {
+        a.copy_from_slice(&wrapped[..8]);
+        key = wrapped[8..].to_vec();
+        unwrap_semiblocks(kek, &mut a, &mut key);
+    }
+
+    // Check the constant, the length and the padding without returning
+    // early, so they all take the same time to fail.
+    let padded_len = key.len();
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&a[4..]);
+    let len = u32::from_be_bytes(len) as usize;
+
+    let mut ok = constant_time_eq(&a[..4], &ICV2);
+    ok &= len > padded_len - 8 && len <= padded_len;
+    let mut padding = 0u8;
+    for (i, b) in key.iter().enumerate() {
+        padding |= *b & 0u8.wrapping_sub((i >= len) as u8);
+    }
+    ok &= padding == 0;
+
+    if !ok {
+        return Err(Error::Authentication);
+    }
+    key.truncate(len);
+    Ok(key)
+}
@@ -1,5 +1,59 @@
 //! Simple AES implementation.
 
+extern crate getrandom;
+
+use std::error;
+use std::fmt;
+
+use ctr::CtrState;
+
+#[cfg(target_arch = "x86_64")]
+mod aesni;
+mod backend;
+mod bitslice;
+mod cbc_cs;
+mod ccm;
+mod cmac;
+mod ctr;
+mod eax;
+mod gcm;
+mod gcm_siv;
+mod kw;
+mod ocb;
+mod padding;
+mod siv;
+mod stream;
+#[cfg(feature = "ttable")]
+mod ttable;
+#[cfg(target_arch = "x86_64")]
+mod vaes;
+#[cfg(target_arch = "x86_64")]
+mod vperm;
+mod xts;
+
+pub use backend::Backend;
+pub use cbc_cs::{
+    aes_cbc_cs1_decrypt_buffer, aes_cbc_cs1_encrypt_buffer, aes_cbc_cs2_decrypt_buffer,
+    aes_cbc_cs2_encrypt_buffer, aes_cbc_cs3_decrypt_buffer, aes_cbc_cs3_encrypt_buffer,
+};
+pub use ccm::{aes_ccm_open, aes_ccm_seal};
+pub use cmac::{aes_cmac, aes_cmac_verify, CMAC};
+pub use ctr::{rfc3686_counter_block, CounterLayout, Endian};
+pub use eax::{aes_eax_open, aes_eax_seal};
+pub use gcm::{aes_gcm_open, aes_gcm_seal};
+pub use gcm_siv::{aes_gcm_siv_open, aes_gcm_siv_seal};
+pub use kw::{aes_kw_unwrap, aes_kw_wrap, aes_kwp_unwrap, aes_kwp_wrap};
+pub use ocb::{aes_ocb_open, aes_ocb_seal};
+pub use padding::{
+    aes_cbc_decrypt_padded, aes_cbc_encrypt_padded, aes_ecb_decrypt_padded,
+    aes_ecb_encrypt_padded, pad, unpad, Padding,
+};
+pub use siv::{aes_siv_open, aes_siv_seal, SIV};
+pub use stream::{CBC, CFB, CTR};
+pub use xts::{
+    aes_xts_decrypt, aes_xts_decrypt_sector, aes_xts_encrypt, aes_xts_encrypt_sector, XTS,
+};
+
 // The number of columns comprising a state in AES. This is a constant in AES. Value=4
 const NB: u8 = 4;
 
@@ -15,23 +69,144 @@ const NR_192: u8 = 12;
 const NK_128: u8 = 4;
 const NR_128: u8 = 10;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     CTR,
     CBC,
     ECB,
+    GCM,
+    CCM,
+    XTS,
+    CFB1,
+    CFB8,
+    CFB128,
+    OFB,
+    CMAC,
+    KW,
+    KWP,
+    SIV,
+    GCMSIV,
+    EAX,
+    OCB,
+    CBCCS1,
+    CBCCS2,
+    CBCCS3,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Size {
     AES128,
     AES192,
     AES256,
 }
 
+impl Size {
+    /// Picks the key size matching a 16, 24 or 32 byte key.
+    pub fn from_key_len(len: usize) -> Result<Size, Error> {
+        match len {
+            16 => Ok(Size::AES128),
+            24 => Ok(Size::AES192),
+            32 => Ok(Size::AES256),
+            _ => Err(Error::BadKeyLength),
+        }
+    }
+
+    /// The key length in bytes.
+    pub fn key_len(&self) -> usize {
+        match *self {
+            Size::AES128 => 16,
+            Size::AES192 => 24,
+            Size::AES256 => 32,
+        }
+    }
+
+    fn nr(&self) -> u8 {
+        match *self {
+            Size::AES128 => NR_128,
+            Size::AES192 => NR_192,
+            Size::AES256 => NR_256,
+        }
+    }
+
+    fn nk(&self) -> u8 {
+        match *self {
+            Size::AES128 => NK_128,
+            Size::AES192 => NK_192,
+            Size::AES256 => NK_256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The authentication tag did not match, the message must be discarded.
+    Authentication,
+    /// The padding of a decrypted message is malformed. Deliberately says
+    /// nothing about what is wrong with it, to not become a padding oracle.
+    BadPadding,
+    /// The buffer length is not a multiple of the block size of the mode, or
+    /// shorter than the minimum it needs.
+    MisalignedBuffer,
+    /// The key does not have the length of the selected key size, or the
+    /// mode does not accept it.
+    BadKeyLength,
+    /// The IV, nonce or tweak has a length the mode does not support.
+    BadIvLength,
+    /// The tag has a length the mode does not support.
+    BadTagLength,
+    /// The message needs more counter blocks than are left.
+    CounterExhausted,
+    /// The CTR counter does not fit into the counter block.
+    BadCounterLayout,
+    /// The message or associated data exceeds the limits of the mode.
+    MessageTooLong,
+    /// The mode needs more than a key and IV, like a nonce, associated data
+    /// or a tag, and can only be used through its own functions.
+    UnsupportedMode,
+    /// The backend can't run on this machine.
+    UnsupportedBackend,
+    /// The operating system could not provide random bytes.
+    RandomUnavailable,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Authentication => write!(f, "authentication failed"),
+            Error::BadPadding => write!(f, "invalid padding"),
+            Error::MisalignedBuffer => write!(f, "invalid buffer length for the mode"),
+            Error::BadKeyLength => write!(f, "invalid key length"),
+            Error::BadIvLength => write!(f, "invalid IV length"),
+            Error::BadTagLength => write!(f, "invalid tag length"),
+            Error::CounterExhausted => write!(f, "counter exhausted"),
+            Error::BadCounterLayout => write!(f, "invalid counter layout"),
+            Error::MessageTooLong => write!(f, "message too long"),
+            Error::UnsupportedMode => write!(f, "mode not supported by this function"),
+            Error::UnsupportedBackend => write!(f, "backend not available"),
+            Error::RandomUnavailable => write!(f, "no random bytes available"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
 pub struct AES {
     pub size: Size,
     pub mode: Mode,
     pub round_key: [u8; 240],
+    /// The IV, or the counter block in CTR mode. The streaming modes keep
+    /// their position in the current block across calls; use `set_iv` to
+    /// start over, as assigning the value `iv` already holds is not noticed.
     pub iv: [u8; 16],
+    // Number of bytes already used from the current block by the streaming
+    // modes, so a partial segment can be continued by the next call.
+    offset: usize,
+    // `iv` as CFB-128 and OFB left it. If `iv` differs from it, a new value
+    // was assigned and those modes start over at a block boundary.
+    register: [u8; 16],
+    // The CTR stream, whose counter block is `iv`. Like `register`, only an
+    // `iv` that differs from the counter the stream left starts a new one.
+    ctr: CtrState,
 }
 
 static SBOX: [u8; 256] = [
@@ -576,6 +751,25 @@ fn get_sbox_invert(num: u8) -> u8 {
     RSBOX[num as usize]
 }
 
+// With the selected backend, so the key bytes, which are secret too, are
+// only looked up in tables by the table-based code.
+fn sub_word(word: &mut [u8; 4]) {
+    match Backend::selected() {
+        Backend::Soft | Backend::TTable => {
+            for b in word.iter_mut() {
+                *b = get_sbox_value(*b);
+            }
+        }
+        Backend::Bitslice => bitslice::sub_word(word),
+        #[cfg(target_arch = "x86_64")]
+        Backend::AesNi | Backend::Vaes => aesni::sub_word(word),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Vperm => vperm::sub_word(word),
+        #[cfg(not(target_arch = "x86_64"))]
+        Backend::AesNi | Backend::Vaes | Backend::Vperm => unreachable!(),
+    }
+}
+
 // This function produces NB(nr+1) round keys. The round keys are used in each round to decrypt the states.
 fn key_expansion(nk: u8, nr: u8, round_key: &mut [u8], key: &[u8]) {
     let mut j: u8;
@@ -584,7 +778,7 @@ fn key_expansion(nk: u8, nr: u8, round_key: &mut [u8], key: &[u8]) {
 
     // The first round key is the key itself.
     for i in 0..nk {
-        round_key[((i * 4) + 0) as usize] = key[((i * 4) + 0) as usize];
+        round_key[(i * 4) as usize] = key[(i * 4) as usize];
         round_key[((i * 4) + 1) as usize] = key[((i * 4) + 1) as usize];
         round_key[((i * 4) + 2) as usize] = key[((i * 4) + 2) as usize];
         round_key[((i * 4) + 3) as usize] = key[((i * 4) + 3) as usize];
@@ -593,7 +787,7 @@ fn key_expansion(nk: u8, nr: u8, round_key: &mut [u8], key: &[u8]) {
     // All other round keys are found from the previous round keys.
     for i in nk..(NB * (nr + 1)) {
         k = (i - 1) * 4;
-        tempa[0] = round_key[(k + 0) as usize];
+        tempa[0] = round_key[k as usize];
         tempa[1] = round_key[(k + 1) as usize];
         tempa[2] = round_key[(k + 2) as usize];
         tempa[3] = round_key[(k + 3) as usize];
@@ -613,25 +807,20 @@ fn key_expansion(nk: u8, nr: u8, round_key: &mut [u8], key: &[u8]) {
             // applies the S-box to each of the four bytes to produce an output word.
 
             // Function Subword()
-            tempa[0] = get_sbox_value(tempa[0]);
-            tempa[1] = get_sbox_value(tempa[1]);
-            tempa[2] = get_sbox_value(tempa[2]);
-            tempa[3] = get_sbox_value(tempa[3]);
+            sub_word(&mut tempa);
 
-            tempa[0] = tempa[0] ^ RCON[(i / nk) as usize];
+            tempa[0] ^= RCON[(i / nk) as usize];
         }
 
-        if i % nk == 4 {
+        // The extra SubWord() step only applies to 256 bit keys.
+        if nk == NK_256 && i % nk == 4 {
             // Function Subword()
-            tempa[0] = get_sbox_value(tempa[0]);
-            tempa[1] = get_sbox_value(tempa[1]);
-            tempa[2] = get_sbox_value(tempa[2]);
-            tempa[3] = get_sbox_value(tempa[3]);
+            sub_word(&mut tempa);
         }
 
         j = i * 4;
         k = (i - nk) * 4;
-        round_key[(j + 0) as usize] = round_key[(k + 0) as usize] ^ tempa[0];
+        round_key[j as usize] = round_key[k as usize] ^ tempa[0];
         round_key[(j + 1) as usize] = round_key[(k + 1) as usize] ^ tempa[1];
         round_key[(j + 2) as usize] = round_key[(k + 2) as usize] ^ tempa[2];
         round_key[(j + 3) as usize] = round_key[(k + 3) as usize] ^ tempa[3];
@@ -639,45 +828,234 @@ fn key_expansion(nk: u8, nr: u8, round_key: &mut [u8], key: &[u8]) {
 }
 
 impl AES {
+    /// Creates a context, panicking if the key or IV has the wrong length.
+    ///
+    /// Use `AES::try_new` for keys that are not known to be valid.
     pub fn new(size: Size, mode: Mode, key: &[u8], iv_raw: &[u8]) -> AES {
-        let mut round_key = [0u8; 240];
-        match size {
-            Size::AES128 => key_expansion(NK_128, NR_128, &mut round_key, key),
-            Size::AES192 => key_expansion(NK_192, NR_192, &mut round_key, key),
-            Size::AES256 => key_expansion(NK_256, NR_256, &mut round_key, key),
+        match AES::try_new(size, mode, key, iv_raw) {
+            Ok(aes) => aes,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a context, returning `Error::BadKeyLength` unless the key has
+    /// 16, 24 or 32 bytes to match `size`, and `Error::BadIvLength` unless
+    /// the IV has 16 bytes.
+    pub fn try_new(size: Size, mode: Mode, key: &[u8], iv_raw: &[u8]) -> Result<AES, Error> {
+        let mut aes = AES {
+            mode,
+            size,
+            round_key: [0u8; 240],
+            iv: [0u8; 16],
+            offset: 0,
+            register: [0u8; 16],
+            ctr: CtrState::new(CounterLayout::FULL, &[0u8; 16]),
         };
+        aes.set_key(key)?;
+        aes.set_iv(iv_raw)?;
+        Ok(aes)
+    }
+
+    /// Creates a context with the key size inferred from the length of the
+    /// key, returning `Error::BadKeyLength` unless it has 16, 24 or 32 bytes.
+    pub fn with_key(mode: Mode, key: &[u8], iv_raw: &[u8]) -> Result<AES, Error> {
+        AES::try_new(Size::from_key_len(key.len())?, mode, key, iv_raw)
+    }
 
-        let mut iv = [0u8; 16];
-        iv.clone_from_slice(iv_raw);
-        AES {
-            mode: mode,
-            size: size,
-            round_key: round_key,
-            iv: iv,
+    /// Creates an AES-128 context. The key and IV can't have a wrong length.
+    pub fn new_128(mode: Mode, key: &[u8; 16], iv: &[u8; 16]) -> AES {
+        AES::new(Size::AES128, mode, key, iv)
+    }
+
+    /// Creates an AES-192 context.
+    pub fn new_192(mode: Mode, key: &[u8; 24], iv: &[u8; 16]) -> AES {
+        AES::new(Size::AES192, mode, key, iv)
+    }
+
+    /// Creates an AES-256 context.
+    pub fn new_256(mode: Mode, key: &[u8; 32], iv: &[u8; 16]) -> AES {
+        AES::new(Size::AES256, mode, key, iv)
+    }
+
+    /// Replaces the key, which must have the length of `self.size`.
+    pub fn set_key(&mut self, key: &[u8]) -> Result<(), Error> {
+        if key.len() != self.size.key_len() {
+            return Err(Error::BadKeyLength);
+        }
+        key_expansion(self.nk(), self.nr(), &mut self.round_key, key);
+        Ok(())
+    }
+
+    /// Replaces the IV, which must have 16 bytes, and restarts the streaming
+    /// modes at the beginning of a block.
+    pub fn set_iv(&mut self, iv_raw: &[u8]) -> Result<(), Error> {
+        if iv_raw.len() != 16 {
+            return Err(Error::BadIvLength);
+        }
+        self.iv.copy_from_slice(iv_raw);
+        self.offset = 0;
+        self.register = self.iv;
+        self.ctr = CtrState::new(self.ctr.layout(), &self.iv);
+        Ok(())
+    }
+
+    /// Selects the part of the IV that CTR increments, starting over from
+    /// the current counter block. The default is `CounterLayout::FULL`.
+    pub fn set_counter_layout(&mut self, layout: CounterLayout) {
+        self.ctr = CtrState::new(layout, &self.iv);
+    }
+
+    /// Moves a CTR context to `byte_offset` bytes past the IV set last, so
+    /// the next call to `aes_ctr_xcrypt_buffer` continues from there.
+    ///
+    /// Returns `Error::UnsupportedMode` for all other modes, and
+    /// `Error::CounterExhausted` if the counter would wrap around. Seeking
+    /// to the end of the keystream succeeds, after which only empty buffers
+    /// can be processed.
+    pub fn seek(&mut self, byte_offset: u64) -> Result<(), Error> {
+        if self.mode != Mode::CTR {
+            return Err(Error::UnsupportedMode);
         }
+        self.ctr.seek(self.size.nr(), &self.round_key, &mut self.iv, byte_offset)
+    }
+
+    /// Encrypts `buf` in place with the mode selected by `self.mode`.
+    ///
+    /// Supports the modes that only need the key and IV in the context:
+    /// ECB, CBC, CBC-CS, CTR, CFB and OFB. The chaining modes continue from
+    /// the IV left by the previous call, like their functions. For CBC-CS
+    /// that is the full last ciphertext block C_n, so every call is a new
+    /// message chained to the previous one. All other modes return
+    /// `Error::UnsupportedMode`.
+    pub fn encrypt(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.xcrypt(buf, false)
     }
 
-    pub fn set_iv(&mut self, iv_raw: &[u8]) {
-        self.iv.clone_from_slice(iv_raw);
+    /// Decrypts `buf` in place with the mode selected by `self.mode`, see
+    /// `AES::encrypt`.
+    pub fn decrypt(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.xcrypt(buf, true)
+    }
+
+    fn xcrypt(&mut self, buf: &mut [u8], decrypt: bool) -> Result<(), Error> {
+        match (self.mode, decrypt) {
+            (Mode::ECB, false) => aes_ecb_encrypt(self, buf),
+            (Mode::ECB, true) => aes_ecb_decrypt(self, buf),
+            (Mode::CBC, false) => aes_cbc_encrypt_buffer(self, buf),
+            (Mode::CBC, true) => aes_cbc_decrypt_buffer(self, buf),
+            (Mode::CBCCS1, _) | (Mode::CBCCS2, _) | (Mode::CBCCS3, _) => {
+                cbc_cs::xcrypt(self, buf, decrypt)
+            }
+            (Mode::CTR, _) => aes_ctr_xcrypt_buffer(self, buf),
+            (Mode::CFB1, _) => {
+                cfb1_xcrypt(self, buf, decrypt);
+                Ok(())
+            }
+            (Mode::CFB8, _) => {
+                cfb8_xcrypt(self, buf, decrypt);
+                Ok(())
+            }
+            (Mode::CFB128, false) => {
+                aes_cfb128_encrypt_buffer(self, buf);
+                Ok(())
+            }
+            (Mode::CFB128, true) => {
+                aes_cfb128_decrypt_buffer(self, buf);
+                Ok(())
+            }
+            (Mode::OFB, _) => {
+                aes_ofb_xcrypt_buffer(self, buf);
+                Ok(())
+            }
+            (Mode::GCM, _)
+            | (Mode::CCM, _)
+            | (Mode::XTS, _)
+            | (Mode::CMAC, _)
+            | (Mode::KW, _)
+            | (Mode::KWP, _)
+            | (Mode::SIV, _)
+            | (Mode::GCMSIV, _)
+            | (Mode::EAX, _)
+            | (Mode::OCB, _) => Err(Error::UnsupportedMode),
+        }
     }
 
     pub fn nr(&self) -> u8 {
-        match self.size {
-            Size::AES128 => NR_128,
-            Size::AES192 => NR_192,
-            Size::AES256 => NR_256,
+        self.size.nr()
+    }
+
+    // Drops the position in the current block if `iv` was replaced since
+    // the last call, so the new register is encrypted before it is used.
+    fn sync_register(&mut self) {
+        if self.iv != self.register {
+            self.offset = 0;
         }
     }
 
     pub fn nk(&self) -> u8 {
-        match self.size {
-            Size::AES128 => NK_128,
-            Size::AES192 => NK_192,
-            Size::AES256 => NK_256,
+        self.size.nk()
+    }
+}
+
+/// An expanded key without any mode state.
+///
+/// It is never modified after construction, so one schedule can be shared
+/// between threads by reference or in an `Arc`, with a `CBC`, `CTR` or
+/// `CFB` per message holding the chaining state.
+#[derive(Clone)]
+pub struct KeySchedule {
+    size: Size,
+    round_key: [u8; 240],
+}
+
+impl KeySchedule {
+    /// Expands `key`, panicking if it doesn't have the length of `size`.
+    pub fn new(size: Size, key: &[u8]) -> KeySchedule {
+        match KeySchedule::try_new(size, key) {
+            Ok(schedule) => schedule,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Expands `key`, returning `Error::BadKeyLength` unless it has the
+    /// length of `size`.
+    pub fn try_new(size: Size, key: &[u8]) -> Result<KeySchedule, Error> {
+        if key.len() != size.key_len() {
+            return Err(Error::BadKeyLength);
         }
+        let mut round_key = [0u8; 240];
+        key_expansion(size.nk(), size.nr(), &mut round_key, key);
+        Ok(KeySchedule { size, round_key })
+    }
+
+    /// Expands `key` with the size inferred from its length.
+    pub fn with_key(key: &[u8]) -> Result<KeySchedule, Error> {
+        KeySchedule::try_new(Size::from_key_len(key.len())?, key)
+    }
+
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Encrypts a single block in place.
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        cipher(self.size.nr(), block, &self.round_key);
+    }
+
+    /// Decrypts a single block in place.
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        inv_cipher(self.size.nr(), block, &self.round_key);
     }
 }
 
+impl<'a> From<&'a AES> for KeySchedule {
+    fn from(ctx: &'a AES) -> KeySchedule {
+        KeySchedule {
+            size: ctx.size,
+            round_key: ctx.round_key,
+        }
+    }
+}
 
 // This function adds the round key to state.
 // The round key is added to the state by an XOR function.
@@ -729,7 +1107,7 @@ fn shift_rows(state: &mut [u8]) {
 
 #[inline]
 fn xtime(x: u8) -> u8 {
-    ((x << 1) ^ (((x >> 7) & 1) * 0x1b))
+    (x << 1) ^ (((x >> 7) & 1) * 0x1b)
 }
 
 // MixColumns function mixes the columns of the state matrix
@@ -762,9 +1140,9 @@ fn mix_columns(state: &mut [u8]) {
 
 // Multiply is used to multiply numbers in the field GF(2^8)
 fn multiply(x: u8, y: u8) -> u8 {
-    (((y & 1) * x) ^ ((y >> 1 & 1) * xtime(x)) ^ ((y >> 2 & 1) * xtime(xtime(x))) ^
-         ((y >> 3 & 1) * xtime(xtime(xtime(x)))) ^
-         ((y >> 4 & 1) * xtime(xtime(xtime(xtime(x))))))
+    ((y & 1) * x) ^ ((y >> 1 & 1) * xtime(x)) ^ ((y >> 2 & 1) * xtime(xtime(x))) ^
+        ((y >> 3 & 1) * xtime(xtime(xtime(x)))) ^
+        ((y >> 4 & 1) * xtime(xtime(xtime(xtime(x)))))
 }
 
 // InvMixColumns function mixes the columns of the state matrix.
@@ -831,7 +1209,63 @@ fn inv_shift_rows(state: &mut [u8]) {
 }
 
 // Cipher is the main function that encrypts the PlainText.
+// Encrypts a single block with the backend from `Backend::selected`.
 fn cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
+    cipher_blocks(nr, &mut state[..16], round_key);
+}
+
+fn inv_cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
+    inv_cipher_blocks(nr, &mut state[..16], round_key);
+}
+
+// Encrypts every block of `blocks`, whose length is a multiple of 16.
+fn cipher_blocks(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    match Backend::selected() {
+        Backend::Soft => {
+            for block in blocks.chunks_mut(16) {
+                soft_cipher(nr, block, round_key);
+            }
+        }
+        Backend::Bitslice => bitslice::encrypt(nr, blocks, round_key),
+        #[cfg(target_arch = "x86_64")]
+        Backend::AesNi => aesni::encrypt(nr, blocks, round_key),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Vaes => vaes::encrypt(nr, blocks, round_key),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Vperm => vperm::encrypt(nr, blocks, round_key),
+        #[cfg(not(target_arch = "x86_64"))]
+        Backend::AesNi | Backend::Vaes | Backend::Vperm => unreachable!(),
+        #[cfg(feature = "ttable")]
+        Backend::TTable => ttable::encrypt(nr, blocks, round_key),
+        #[cfg(not(feature = "ttable"))]
+        Backend::TTable => unreachable!(),
+    }
+}
+
+fn inv_cipher_blocks(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    match Backend::selected() {
+        Backend::Soft => {
+            for block in blocks.chunks_mut(16) {
+                soft_inv_cipher(nr, block, round_key);
+            }
+        }
+        Backend::Bitslice => bitslice::decrypt(nr, blocks, round_key),
+        #[cfg(target_arch = "x86_64")]
+        Backend::AesNi => aesni::decrypt(nr, blocks, round_key),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Vaes => vaes::decrypt(nr, blocks, round_key),
+        #[cfg(target_arch = "x86_64")]
+        Backend::Vperm => vperm::decrypt(nr, blocks, round_key),
+        #[cfg(not(target_arch = "x86_64"))]
+        Backend::AesNi | Backend::Vaes | Backend::Vperm => unreachable!(),
+        #[cfg(feature = "ttable")]
+        Backend::TTable => ttable::decrypt(nr, blocks, round_key),
+        #[cfg(not(feature = "ttable"))]
+        Backend::TTable => unreachable!(),
+    }
+}
+
+fn soft_cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
     // Add the First round key to the state before starting the rounds.
     add_round_key(0, state, round_key);
 
@@ -852,7 +1286,7 @@ fn cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
     add_round_key(nr, state, round_key);
 }
 
-fn inv_cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
+fn soft_inv_cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
     // Add the First round key to the state before starting the rounds.
     add_round_key(nr, state, round_key);
 
@@ -874,74 +1308,247 @@ fn inv_cipher(nr: u8, state: &mut [u8], round_key: &[u8]) {
 }
 
 
-pub fn aes_ecb_encrypt(ctx: &AES, buf: &mut [u8]) {
-    // The next function call encrypts the PlainText with the Key using AES algorithm.
-    cipher(ctx.nr(), buf, &ctx.round_key);
+// ECB on every block of `buf`, whose length is a multiple of 16.
+fn ecb_xcrypt(ctx: &AES, buf: &mut [u8], decrypt: bool) {
+    if decrypt {
+        inv_cipher_blocks(ctx.nr(), buf, &ctx.round_key);
+    } else {
+        cipher_blocks(ctx.nr(), buf, &ctx.round_key);
+    }
+}
+
+fn check_aligned(len: usize) -> Result<(), Error> {
+    if !len.is_multiple_of(16) {
+        return Err(Error::MisalignedBuffer);
+    }
+    Ok(())
+}
+
+/// Encrypts every block of `buf` in place.
+///
+/// Returns `Error::MisalignedBuffer` if the length of `buf` is not a
+/// multiple of 16, leaving it untouched.
+pub fn aes_ecb_encrypt(ctx: &AES, buf: &mut [u8]) -> Result<(), Error> {
+    check_aligned(buf.len())?;
+    ecb_xcrypt(ctx, buf, false);
+    Ok(())
+}
+
+/// Decrypts every block of `buf` in place, see `aes_ecb_encrypt`.
+pub fn aes_ecb_decrypt(ctx: &AES, buf: &mut [u8]) -> Result<(), Error> {
+    check_aligned(buf.len())?;
+    ecb_xcrypt(ctx, buf, true);
+    Ok(())
+}
+
+/// Encrypts `input` into `output`, which must have the same length.
+///
+/// Returns `Error::MisalignedBuffer` if the lengths differ or are not a
+/// multiple of 16.
+pub fn aes_ecb_encrypt_to(ctx: &AES, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    if input.len() != output.len() {
+        return Err(Error::MisalignedBuffer);
+    }
+    check_aligned(input.len())?;
+    output.copy_from_slice(input);
+    ecb_xcrypt(ctx, output, false);
+    Ok(())
+}
+
+/// Decrypts `input` into `output`, which must have the same length.
+pub fn aes_ecb_decrypt_to(ctx: &AES, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    if input.len() != output.len() {
+        return Err(Error::MisalignedBuffer);
+    }
+    check_aligned(input.len())?;
+    output.copy_from_slice(input);
+    ecb_xcrypt(ctx, output, true);
+    Ok(())
 }
 
-pub fn aes_ecb_decrypt(ctx: &AES, buf: &mut [u8]) {
-    // The next function call decrypts the PlainText with the Key using AES algorithm.
-    inv_cipher(ctx.nr(), buf, &ctx.round_key);
+// Compares two tags without branching on their contents, so the time taken
+// does not reveal how many leading bytes matched.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
 }
 
 fn xor_with_iv(buf: &mut [u8], iv: &[u8]) {
     // The block in AES is always 128bit no matter the key size
-    for i in 0..16 {
-        buf[i] ^= iv[i];
-    }
+    let (mut a, mut b) = ([0u8; 16], [0u8; 16]);
+    a.copy_from_slice(&buf[..16]);
+    b.copy_from_slice(&iv[..16]);
+    let x = u128::from_ne_bytes(a) ^ u128::from_ne_bytes(b);
+    buf[..16].copy_from_slice(&x.to_ne_bytes());
 }
 
-pub fn aes_cbc_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
-    // uint8_t *Iv = ctx->Iv;
-    let mut hist = [0u8; 16];
-    hist.copy_from_slice(&ctx.iv);
-    let mut iv: &mut [u8] = &mut hist;
-    for chunk in buf.chunks_mut(16 as usize) {
-        xor_with_iv(chunk, &iv);
-        cipher(ctx.nr(), chunk, &ctx.round_key);
-        iv = chunk;
-        //printf("Step %d - %d", i/16, i);
+// CBC on every block of `buf`, whose length is a multiple of 16.
+// The last ciphertext block is left in `iv` for the next call.
+fn cbc_encrypt(nr: u8, round_key: &[u8], iv: &mut [u8; 16], buf: &mut [u8]) {
+    for chunk in buf.chunks_mut(16) {
+        xor_with_iv(chunk, iv);
+        cipher(nr, chunk, round_key);
+        iv.copy_from_slice(chunk);
     }
-    /* store Iv in ctx for next call */
-    ctx.iv.copy_from_slice(iv)
 }
 
-pub fn aes_cbc_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
-    let mut next_iv = [0u8; 16];
-    for chunk in buf.chunks_mut(16 as usize) {
-        next_iv.copy_from_slice(chunk);
-        inv_cipher(ctx.nr(), chunk, &ctx.round_key);
-        xor_with_iv(chunk, &ctx.iv);
-        ctx.iv.copy_from_slice(&next_iv);
+// The blocks are independent when decrypting, so they are deciphered up to
+// 16 at a time and the ciphertext is kept to xor them with.
+fn cbc_decrypt(nr: u8, round_key: &[u8], iv: &mut [u8; 16], buf: &mut [u8]) {
+    let mut saved = [0u8; 256];
+    for chunk in buf.chunks_mut(256) {
+        let saved = &mut saved[..chunk.len()];
+        saved.copy_from_slice(chunk);
+        inv_cipher_blocks(nr, chunk, round_key);
+        xor_with_iv(chunk, iv);
+        for (block, prev) in chunk[16..].chunks_mut(16).zip(saved.chunks(16)) {
+            xor_with_iv(block, prev);
+        }
+        iv.copy_from_slice(&saved[saved.len() - 16..]);
     }
 }
 
+/// Encrypts `buf` in place with CBC, continuing from the IV in `ctx`.
+///
+/// Returns `Error::MisalignedBuffer` if the length of `buf` is not a
+/// multiple of 16, see the CBC-CS functions for other lengths.
+pub fn aes_cbc_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    check_aligned(buf.len())?;
+    cbc_encrypt(ctx.nr(), &ctx.round_key, &mut ctx.iv, buf);
+    Ok(())
+}
+
+/// Decrypts `buf` in place with CBC, see `aes_cbc_encrypt_buffer`.
+pub fn aes_cbc_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    check_aligned(buf.len())?;
+    cbc_decrypt(ctx.nr(), &ctx.round_key, &mut ctx.iv, buf);
+    Ok(())
+}
 
 // Symmetrical operation: same function for encrypting as for decrypting.
 // Note: Any IV/nonce should never be reused with the same key
-pub fn aes_ctr_xcrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
-    let mut bi = 16;
-    let mut buffer = [0u8; 16];
-
-    for i in 0..buf.len() {
-        /* we need to regen xor complement in buffer */
-        if bi == 16 {
-            buffer.copy_from_slice(&ctx.iv);
-            cipher(ctx.nr(), &mut buffer, &ctx.round_key);
-
-            /* Increment Iv and handle overflow */
-            for el in ctx.iv.iter_mut().rev() {
-                if *el == 255 {
-                    *el = 0;
-                    continue;
-                }
-                *el += 1;
-                break;
+//
+// A partial keystream block is kept in the context, so a stream can be
+// processed in chunks of any length. The counter is incremented as set with
+// `AES::set_counter_layout`.
+pub fn aes_ctr_xcrypt_buffer(ctx: &mut AES, buf: &mut [u8]) -> Result<(), Error> {
+    ctx.ctr.xcrypt(ctx.size.nr(), &ctx.round_key, &mut ctx.iv, buf)
+}
+
+// CFB-1: every segment is a single bit. The bits of each byte are processed
+// starting with the most significant one, and every bit costs a full block
+// encryption.
+fn cfb1_xcrypt(ctx: &mut AES, buf: &mut [u8], decrypt: bool) {
+    let mut block = [0u8; 16];
+    for b in buf.iter_mut() {
+        let mut out = 0u8;
+        for bit in (0..8).rev() {
+            block.copy_from_slice(&ctx.iv);
+            cipher(ctx.nr(), &mut block, &ctx.round_key);
+
+            let input = (*b >> bit) & 1;
+            let output = input ^ (block[0] >> 7);
+            out |= output << bit;
+
+            /* shift the ciphertext bit into the register */
+            let c = if decrypt { input } else { output };
+            for i in 0..15 {
+                ctx.iv[i] = (ctx.iv[i] << 1) | (ctx.iv[i + 1] >> 7);
             }
-            bi = 0;
+            ctx.iv[15] = (ctx.iv[15] << 1) | c;
         }
+        *b = out;
+    }
+}
+
+pub fn aes_cfb1_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    cfb1_xcrypt(ctx, buf, false);
+}
+
+pub fn aes_cfb1_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    cfb1_xcrypt(ctx, buf, true);
+}
 
-        buf[i] = buf[i] ^ buffer[bi as usize];
-        bi += 1;
+// CFB-8: every segment is a single byte.
+fn cfb8_xcrypt(ctx: &mut AES, buf: &mut [u8], decrypt: bool) {
+    let mut block = [0u8; 16];
+    for b in buf.iter_mut() {
+        block.copy_from_slice(&ctx.iv);
+        cipher(ctx.nr(), &mut block, &ctx.round_key);
+
+        let c = if decrypt { *b } else { *b ^ block[0] };
+        *b ^= block[0];
+
+        /* shift the ciphertext byte into the register */
+        ctx.iv.copy_within(1.., 0);
+        ctx.iv[15] = c;
+    }
+}
+
+pub fn aes_cfb8_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    cfb8_xcrypt(ctx, buf, false);
+}
+
+pub fn aes_cfb8_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    cfb8_xcrypt(ctx, buf, true);
+}
+
+// CFB-128: every segment is a full block. At the start of a block the
+// register is replaced by its encryption, which is then overwritten byte
+// by byte with the ciphertext. Once the block is complete the register
+// holds the ciphertext block again, like the iv in CBC.
+fn cfb128_xcrypt(
+    nr: u8,
+    round_key: &[u8],
+    register: &mut [u8; 16],
+    offset: &mut usize,
+    buf: &mut [u8],
+    decrypt: bool,
+) {
+    for b in buf.iter_mut() {
+        if *offset == 0 {
+            cipher(nr, register, round_key);
+        }
+
+        let c = if decrypt { *b } else { *b ^ register[*offset] };
+        *b ^= register[*offset];
+        register[*offset] = c;
+        *offset = (*offset + 1) % 16;
+    }
+}
+
+pub fn aes_cfb128_encrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    ctx.sync_register();
+    cfb128_xcrypt(ctx.nr(), &ctx.round_key, &mut ctx.iv, &mut ctx.offset, buf, false);
+    ctx.register = ctx.iv;
+}
+
+pub fn aes_cfb128_decrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    ctx.sync_register();
+    cfb128_xcrypt(ctx.nr(), &ctx.round_key, &mut ctx.iv, &mut ctx.offset, buf, true);
+    ctx.register = ctx.iv;
+}
+
+// OFB: the register is encrypted over and over again and the results are
+// used as keystream, so this is a symmetrical operation like CTR.
+// The register lives in ctx.iv, together with the position in the current
+// block, so a buffer that ends mid-block is continued by the next call,
+// unless ctx.iv was set to a different value in between.
+// Note: Any IV should never be reused with the same key
+pub fn aes_ofb_xcrypt_buffer(ctx: &mut AES, buf: &mut [u8]) {
+    ctx.sync_register();
+    for b in buf.iter_mut() {
+        if ctx.offset == 0 {
+            cipher(ctx.nr(), &mut ctx.iv, &ctx.round_key);
+        }
+        *b ^= ctx.iv[ctx.offset];
+        ctx.offset = (ctx.offset + 1) % 16;
     }
+    ctx.register = ctx.iv;
 }
@@ -0,0 +1,208 @@
+//! OCB3 authenticated encryption, as specified in RFC 7253.
+
+use super::cmac::dbl;
+use super::{cipher, constant_time_eq, inv_cipher, Error, AES};
+
+fn xor_block(a: &mut [u8], b: &[u8]) {
+    for (x, y) in a.iter_mut().zip(b.iter()) {
+        *x ^= y;
+    }
+}
+
+// The offset table: L_* = ENCIPHER(K, zeros(128)), L_$ = double(L_*),
+// L_0 = double(L_$) and L_i = double(L_{i-1}). Only as many L_i are
+// computed as the longest input needs.
+struct Offsets {
+    l_star: [u8; 16],
+    l_dollar: [u8; 16],
+    l: Vec<[u8; 16]>,
+}
+
+impl Offsets {
+    fn new(ctx: &AES, max_len: usize) -> Offsets {
+        let mut l_star = [0u8; 16];
+        cipher(ctx.nr(), &mut l_star, &ctx.round_key);
+        let l_dollar = dbl(&l_star);
+
+        // Block i uses L_{ntz(i)}, so floor(log2(m)) + 1 entries suffice.
+        let blocks = max_len / 16;
+        let count = (usize::BITS - blocks.leading_zeros()) as usize;
+        let mut l = Vec::with_capacity(count);
+        let mut next = dbl(&l_dollar);
+        for _ in 0..count {
+            l.push(next);
+            next = dbl(&next);
+        }
+
+        Offsets {
+            l_star,
+            l_dollar,
+            l,
+        }
+    }
+
+    fn l(&self, i: usize) -> &[u8; 16] {
+        &self.l[i.trailing_zeros() as usize]
+    }
+}
+
+// HASH(K, A), the contribution of the associated data to the tag.
+fn hash(ctx: &AES, offsets: &Offsets, aad: &[u8]) -> [u8; 16] {
+    let mut sum = [0u8; 16];
+    let mut offset = [0u8; 16];
+
+    let mut chunks = aad.chunks_exact(16);
+    for (i, chunk) in chunks.by_ref().enumerate() {
+        xor_block(&mut offset, offsets.l(i + 1));
+        let mut block = offset;
+        xor_block(&mut block, chunk);
+        cipher(ctx.nr(), &mut block, &ctx.round_key);
+        xor_block(&mut sum, &block);
+    }
+
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        xor_block(&mut offset, &offsets.l_star);
+        let mut block = offset;
+        xor_block(&mut block, rest);
+        block[rest.len()] ^= 0x80;
+        cipher(ctx.nr(), &mut block, &ctx.round_key);
+        xor_block(&mut sum, &block);
+    }
+    sum
+}
+
+// Computes Offset_0 from the nonce and the tag length.
+fn initial_offset(ctx: &AES, nonce: &[u8], tag_len: usize) -> [u8; 16] {
+    // Nonce = num2str(TAGLEN mod 128, 7) || zeros || 1 || N
+    let mut block = [0u8; 16];
+    block[0] = (((tag_len * 8) % 128) << 1) as u8;
+    block[15 - nonce.len()] |= 1;
+    block[16 - nonce.len()..].copy_from_slice(nonce);
+
+    let bottom = (block[15] & 0x3f) as usize;
+    block[15] &= 0xc0;
+    cipher(ctx.nr(), &mut block, &ctx.round_key);
+
+    // Stretch = Ktop || (Ktop[1..64] xor Ktop[9..72])
+    let mut stretch = [0u8; 24];
+    stretch[..16].copy_from_slice(&block);
+    for i in 0..8 {
+        stretch[16 + i] = block[i] ^ block[i + 1];
+    }
+
+    // Offset_0 = Stretch[1+bottom..128+bottom]
+    let (bytes, bits) = (bottom / 8, bottom % 8);
+    let mut offset = [0u8; 16];
+    for (i, o) in offset.iter_mut().enumerate() {
+        *o = stretch[i + bytes] << bits;
+        if bits != 0 {
+            *o |= stretch[i + bytes + 1] >> (8 - bits);
+        }
+    }
+    offset
+}
+
+fn check_params(nonce: &[u8], tag_len: usize) -> Result<(), Error> {
+    if nonce.is_empty() || nonce.len() > 15 {
+        return Err(Error::BadIvLength);
+    }
+    if !(tag_len == 8 || tag_len == 12 || tag_len == 16) {
+        return Err(Error::BadTagLength);
+    }
+    Ok(())
+}
+
+// Encrypts or decrypts `buf` in place and returns the full tag, which is
+// computed over the plaintext checksum.
+fn crypt(
+    ctx: &AES,
+    nonce: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag_len: usize,
+    decrypt: bool,
+) -> [u8; 16] {
+    let offsets = Offsets::new(ctx, aad.len().max(buf.len()));
+    let mut offset = initial_offset(ctx, nonce, tag_len);
+    let mut checksum = [0u8; 16];
+
+    let full = buf.len() - buf.len() % 16;
+    let (blocks, rest) = buf.split_at_mut(full);
+    for (i, chunk) in blocks.chunks_mut(16).enumerate() {
+        xor_block(&mut offset, offsets.l(i + 1));
+        if !decrypt {
+            xor_block(&mut checksum, chunk);
+        }
+        xor_block(chunk, &offset);
+        if decrypt {
+            inv_cipher(ctx.nr(), chunk, &ctx.round_key);
+        } else {
+            cipher(ctx.nr(), chunk, &ctx.round_key);
+        }
+        xor_block(chunk, &offset);
+        if decrypt {
+            xor_block(&mut checksum, chunk);
+        }
+    }
+
+    if !rest.is_empty() {
+        xor_block(&mut offset, &offsets.l_star);
+        let mut pad = offset;
+        cipher(ctx.nr(), &mut pad, &ctx.round_key);
+        if !decrypt {
+            xor_block(&mut checksum, rest);
+        }
+        xor_block(rest, &pad);
+        if decrypt {
+            xor_block(&mut checksum, rest);
+        }
+        checksum[rest.len()] ^= 0x80;
+    }
+
+    // Tag = ENCIPHER(K, Checksum xor Offset xor L_$) xor HASH(K, A)
+    xor_block(&mut checksum, &offset);
+    xor_block(&mut checksum, &offsets.l_dollar);
+    cipher(ctx.nr(), &mut checksum, &ctx.round_key);
+    xor_block(&mut checksum, &hash(ctx, &offsets, aad));
+    checksum
+}
+
+/// Encrypts `buf` in place and writes the authentication tag into `tag`.
+///
+/// The nonce can be up to 120 bits long, the tag 64, 96 or 128 bits.
+pub fn aes_ocb_seal(
+    ctx: &AES,
+    nonce: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error> {
+    check_params(nonce, tag.len())?;
+
+    let tag_len = tag.len();
+    let full_tag = crypt(ctx, nonce, aad, buf, tag_len, false);
+    tag.copy_from_slice(&full_tag[..tag_len]);
+    Ok(())
+}
+
+/// Decrypts `buf` in place and verifies `tag`.
+///
+/// On a tag mismatch `buf` is restored to the ciphertext, so no unverified
+/// plaintext is released, and `Error::Authentication` is returned.
+pub fn aes_ocb_open(
+    ctx: &AES,
+    nonce: &[u8],
+    aad: &[u8],
+    buf: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error> {
+    check_params(nonce, tag.len())?;
+
+    let full_tag = crypt(ctx, nonce, aad, buf, tag.len(), true);
+    if !constant_time_eq(&full_tag[..tag.len()], tag) {
+        crypt(ctx, nonce, aad, buf, tag.len(), false);
+        return Err(Error::Authentication);
+    }
+    Ok(())
+}
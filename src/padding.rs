@@ -0,0 +1,169 @@
+//! Block padding schemes for the ECB and CBC modes: PKCS#7 (RFC 5652),
+//! ISO/IEC 7816-4, ANSI X9.23, ISO 10126 and zero padding.
+
+use super::{cbc_decrypt, cbc_encrypt, ecb_xcrypt, Error, AES};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// n bytes of value n.
+    PKCS7,
+    /// A 0x80 byte followed by zeros.
+    ISO7816,
+    /// Zeros followed by a byte holding the padding length.
+    X923,
+    /// Random bytes followed by a byte holding the padding length.
+    ISO10126,
+    /// Zeros up to the next block boundary, nothing if the data is already
+    /// aligned. Trailing zeros of the data can't be told apart from it.
+    Zero,
+}
+
+// 0xff if a == b, 0 otherwise, without branching on the values.
+fn ct_eq(a: u8, b: u8) -> u8 {
+    (((a ^ b) as u16).wrapping_sub(1) >> 8) as u8
+}
+
+// 0xff if a <= b, 0 otherwise.
+fn ct_le(a: u8, b: u8) -> u8 {
+    !(((b as u16).wrapping_sub(a as u16) >> 8) as u8)
+}
+
+/// Pads `buf` to a multiple of 16 bytes.
+///
+/// All schemes but `Padding::Zero` add a full block if `buf` is already
+/// aligned. `Padding::ISO10126` returns `Error::RandomUnavailable` if the
+/// operating system has no random bytes, leaving `buf` unchanged.
+pub fn pad(padding: Padding, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let n = 16 - buf.len() % 16;
+    match padding {
+        Padding::PKCS7 => buf.resize(buf.len() + n, n as u8),
+        Padding::ISO7816 => {
+            buf.push(0x80);
+            buf.resize(buf.len() + n - 1, 0);
+        }
+        Padding::X923 => {
+            buf.resize(buf.len() + n - 1, 0);
+            buf.push(n as u8);
+        }
+        Padding::ISO10126 => {
+            let mut filler = [0u8; 15];
+            getrandom::getrandom(&mut filler[..n - 1]).map_err(|_| Error::RandomUnavailable)?;
+            buf.extend_from_slice(&filler[..n - 1]);
+            buf.push(n as u8);
+        }
+        Padding::Zero => {
+            if n != 16 {
+                buf.resize(buf.len() + n, 0);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks and strips the padding of `buf`.
+///
+/// The padding is checked in constant time, and every kind of malformed
+/// padding, including a length that isn't a multiple of 16, is reported as
+/// the same `Error::BadPadding`. `buf` is left unchanged on errors.
+pub fn unpad(padding: Padding, buf: &mut Vec<u8>) -> Result<(), Error> {
+    if padding == Padding::Zero && buf.is_empty() {
+        return Ok(());
+    }
+    if buf.is_empty() || !buf.len().is_multiple_of(16) {
+        return Err(Error::BadPadding);
+    }
+
+    let mut block = [0u8; 16];
+    block.copy_from_slice(&buf[buf.len() - 16..]);
+    let last = block[15];
+
+    // Every scheme computes the padding length n and a mask that is 0xff
+    // if the padding is valid, looking at all 16 bytes every time.
+    let (n, valid) = match padding {
+        Padding::PKCS7 | Padding::X923 | Padding::ISO10126 => {
+            let mut valid = !ct_eq(last, 0) & ct_le(last, 16);
+            if padding != Padding::ISO10126 {
+                let mut bad = 0u8;
+                for (i, b) in block[..15].iter().enumerate() {
+                    let in_padding = ct_le(16 - i as u8, last);
+                    let expected = if padding == Padding::PKCS7 { last } else { 0 };
+                    bad |= in_padding & (b ^ expected);
+                }
+                valid &= ct_eq(bad, 0);
+            }
+            (last, valid)
+        }
+        Padding::ISO7816 | Padding::Zero => {
+            // Find the last non-zero byte. ISO 7816-4 requires it to be
+            // the 0x80 marker, which belongs to the padding.
+            let mut n = 0u8;
+            let mut seen = 0u8;
+            let mut marker = 0u8;
+            for (i, b) in block.iter().enumerate().rev() {
+                let first = !ct_eq(*b, 0) & !seen;
+                n |= first & (16 - i as u8);
+                marker |= first & ct_eq(*b, 0x80);
+                seen |= first;
+            }
+            if padding == Padding::ISO7816 {
+                (n, seen & marker)
+            } else {
+                // n - 1 trailing zeros. Zero padding adds at most 15, so
+                // an all zero block only loses 15 of them.
+                ((seen & n.wrapping_sub(1)) | (!seen & 15), 0xff)
+            }
+        }
+    };
+
+    if valid != 0xff {
+        return Err(Error::BadPadding);
+    }
+    let len = buf.len() - n as usize;
+    buf.truncate(len);
+    Ok(())
+}
+
+/// Pads `buf` and encrypts it with CBC, continuing from the IV in `ctx`.
+/// Fails only if `pad` does.
+pub fn aes_cbc_encrypt_padded(
+    ctx: &mut AES,
+    padding: Padding,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    pad(padding, buf)?;
+    cbc_encrypt(ctx.nr(), &ctx.round_key, &mut ctx.iv, buf);
+    Ok(())
+}
+
+/// Decrypts `buf` with CBC and strips the padding.
+///
+/// Returns `Error::BadPadding` for a length that isn't a multiple of 16 or
+/// a malformed padding, in which case `buf` holds the decrypted data.
+pub fn aes_cbc_decrypt_padded(
+    ctx: &mut AES,
+    padding: Padding,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    if !buf.len().is_multiple_of(16) {
+        return Err(Error::BadPadding);
+    }
+    cbc_decrypt(ctx.nr(), &ctx.round_key, &mut ctx.iv, buf);
+    unpad(padding, buf)
+}
+
+/// Pads `buf` and encrypts it with ECB. Fails only if `pad` does.
+pub fn aes_ecb_encrypt_padded(ctx: &AES, padding: Padding, buf: &mut Vec<u8>) -> Result<(), Error> {
+    pad(padding, buf)?;
+    ecb_xcrypt(ctx, buf, false);
+    Ok(())
+}
+
+/// Decrypts `buf` with ECB and strips the padding, like
+/// `aes_cbc_decrypt_padded`.
+pub fn aes_ecb_decrypt_padded(ctx: &AES, padding: Padding, buf: &mut Vec<u8>) -> Result<(), Error> {
+    if !buf.len().is_multiple_of(16) {
+        return Err(Error::BadPadding);
+    }
+    ecb_xcrypt(ctx, buf, true);
+    unpad(padding, buf)
+}
@@ -0,0 +1,138 @@
+//! Synthetic IV (SIV) authenticated encryption, as specified in RFC 5297.
+
+use super::cmac::{dbl, CMAC};
+use super::{cipher, constant_time_eq, Error, Mode, Size, AES};
+
+/// The two halves of a SIV key: `mac` keys S2V, `ctr` keys the encryption.
+pub struct SIV {
+    pub mac: AES,
+    pub ctr: AES,
+}
+
+impl SIV {
+    /// Splits a 32, 48 or 64 byte key into K1 and K2, panicking on other
+    /// lengths.
+    pub fn new(key: &[u8]) -> SIV {
+        match SIV::try_new(key) {
+            Ok(siv) => siv,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Splits a 32, 48 or 64 byte key into K1 and K2, returning
+    /// `Error::BadKeyLength` on other lengths.
+    pub fn try_new(key: &[u8]) -> Result<SIV, Error> {
+        let size = match key.len() {
+            32 => Size::AES128,
+            48 => Size::AES192,
+            64 => Size::AES256,
+            _ => return Err(Error::BadKeyLength),
+        };
+        let (k1, k2) = key.split_at(key.len() / 2);
+        Ok(SIV {
+            mac: AES::try_new(size, Mode::SIV, k1, &[0u8; 16])?,
+            ctr: AES::try_new(size, Mode::SIV, k2, &[0u8; 16])?,
+        })
+    }
+}
+
+fn xor_block(a: &mut [u8; 16], b: &[u8]) {
+    for (x, y) in a.iter_mut().zip(b.iter()) {
+        *x ^= y;
+    }
+}
+
+// S2V, the vector input PRF over the associated data components and the
+// plaintext, which is always the last component.
+fn s2v(ctx: &AES, ad: &[&[u8]], plain: &[u8]) -> [u8; 16] {
+    // D = CMAC(<zero>), the MAC of a block of zeros.
+    let mut d = {
+        let mut mac = CMAC::new(ctx);
+        mac.update(&[0u8; 16]);
+        mac.finalize()
+    };
+
+    for s in ad.iter() {
+        let mut mac = CMAC::new(ctx);
+        mac.update(s);
+        d = dbl(&d);
+        xor_block(&mut d, &mac.finalize());
+    }
+
+    let mut mac = CMAC::new(ctx);
+    if plain.len() >= 16 {
+        // T = Sn xorend D
+        let (head, tail) = plain.split_at(plain.len() - 16);
+        mac.update(head);
+        xor_block(&mut d, tail);
+        mac.update(&d);
+    } else {
+        // T = dbl(D) xor pad(Sn)
+        d = dbl(&d);
+        xor_block(&mut d, plain);
+        d[plain.len()] ^= 0x80;
+        mac.update(&d);
+    }
+    mac.finalize()
+}
+
+// CTR mode starting at the synthetic IV with bits 31 and 63 cleared, so
+// implementations with 64 bit or 32 bit counters interoperate.
+fn ctr(ctx: &AES, v: &[u8; 16], buf: &mut [u8]) {
+    let mut q = *v;
+    q[8] &= 0x7f;
+    q[12] &= 0x7f;
+    let mut counter = u128::from_be_bytes(q);
+
+    for chunk in buf.chunks_mut(16) {
+        let mut keystream = counter.to_be_bytes();
+        cipher(ctx.nr(), &mut keystream, &ctx.round_key);
+        for (b, k) in chunk.iter_mut().zip(keystream.iter()) {
+            *b ^= k;
+        }
+        counter = counter.wrapping_add(1);
+    }
+}
+
+fn check_params(ad: &[&[u8]]) -> Result<(), Error> {
+    // S2V takes at most 127 components, one of them is the plaintext.
+    if ad.len() > 126 {
+        return Err(Error::MessageTooLong);
+    }
+    Ok(())
+}
+
+/// Encrypts `buf` in place and returns the synthetic IV in `siv`.
+///
+/// `ad` holds the associated data components. For nonce based use, the
+/// nonce is passed as the last of them. Without a nonce the encryption is
+/// deterministic. More than 126 components are rejected with
+/// `Error::MessageTooLong`.
+pub fn aes_siv_seal(
+    ctx: &SIV,
+    ad: &[&[u8]],
+    buf: &mut [u8],
+    siv: &mut [u8; 16],
+) -> Result<(), Error> {
+    check_params(ad)?;
+
+    *siv = s2v(&ctx.mac, ad, buf);
+    ctr(&ctx.ctr, siv, buf);
+    Ok(())
+}
+
+/// Decrypts `buf` in place and verifies the synthetic IV.
+///
+/// On a mismatch `buf` is restored to the ciphertext, so no unverified
+/// plaintext is released, and `Error::Authentication` is returned.
+pub fn aes_siv_open(ctx: &SIV, ad: &[&[u8]], buf: &mut [u8], siv: &[u8; 16]) -> Result<(), Error> {
+    check_params(ad)?;
+
+    ctr(&ctx.ctr, siv, buf);
+    let expected = s2v(&ctx.mac, ad, buf);
+    if !constant_time_eq(&expected, siv) {
+        ctr(&ctx.ctr, siv, buf);
+        return Err(Error::Authentication);
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Per-message state for the CBC, CTR and CFB-128 modes of NIST SP 800-38A,
+//! borrowing a shared `KeySchedule`.
+
+use super::ctr::CtrState;
+use super::{cbc_decrypt, cbc_encrypt, cfb128_xcrypt, check_aligned};
+use super::{CounterLayout, Error, KeySchedule};
+
+/// CBC state for one message. Calls continue the chain left by the
+/// previous one.
+pub struct CBC<'a> {
+    key: &'a KeySchedule,
+    iv: [u8; 16],
+}
+
+impl<'a> CBC<'a> {
+    pub fn new(key: &'a KeySchedule, iv: &[u8; 16]) -> CBC<'a> {
+        CBC { key, iv: *iv }
+    }
+
+    /// Encrypts `buf` in place, returning `Error::MisalignedBuffer` unless
+    /// its length is a multiple of 16.
+    pub fn encrypt(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf.len())?;
+        cbc_encrypt(self.key.size.nr(), &self.key.round_key, &mut self.iv, buf);
+        Ok(())
+    }
+
+    pub fn decrypt(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        check_aligned(buf.len())?;
+        cbc_decrypt(self.key.size.nr(), &self.key.round_key, &mut self.iv, buf);
+        Ok(())
+    }
+}
+
+/// CTR state for one message. Buffers of any length can be passed, the
+/// output is the same as for the whole message at once.
+pub struct CTR<'a> {
+    key: &'a KeySchedule,
+    counter: [u8; 16],
+    state: CtrState,
+}
+
+impl<'a> CTR<'a> {
+    /// Starts at `counter`, which is incremented as a 128-bit big-endian
+    /// integer.
+    pub fn new(key: &'a KeySchedule, counter: &[u8; 16]) -> CTR<'a> {
+        CTR::with_layout(key, counter, CounterLayout::FULL)
+    }
+
+    /// Starts at `counter`, incrementing the part of it selected by
+    /// `layout`.
+    pub fn with_layout(key: &'a KeySchedule, counter: &[u8; 16], layout: CounterLayout) -> CTR<'a> {
+        CTR {
+            key,
+            counter: *counter,
+            state: CtrState::new(layout, counter),
+        }
+    }
+
+    /// Encrypts or decrypts `buf` in place. Returns
+    /// `Error::CounterExhausted`, leaving `buf` unchanged, if the counter
+    /// would wrap around.
+    pub fn xcrypt(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let nr = self.key.size.nr();
+        self.state
+            .xcrypt(nr, &self.key.round_key, &mut self.counter, buf)
+    }
+
+    /// Moves to `byte_offset` bytes past the start of the message, with the
+    /// same errors as `AES::seek`.
+    pub fn seek(&mut self, byte_offset: u64) -> Result<(), Error> {
+        let nr = self.key.size.nr();
+        self.state
+            .seek(nr, &self.key.round_key, &mut self.counter, byte_offset)
+    }
+}
+
+/// CFB-128 state for one message. Like `CTR`, buffers of any length can be
+/// passed.
+pub struct CFB<'a> {
+    key: &'a KeySchedule,
+    register: [u8; 16],
+    offset: usize,
+}
+
+impl<'a> CFB<'a> {
+    pub fn new(key: &'a KeySchedule, iv: &[u8; 16]) -> CFB<'a> {
+        CFB {
+            key,
+            register: *iv,
+            offset: 0,
+        }
+    }
+
+    pub fn encrypt(&mut self, buf: &mut [u8]) {
+        cfb128_xcrypt(
+            self.key.size.nr(),
+            &self.key.round_key,
+            &mut self.register,
+            &mut self.offset,
+            buf,
+            false,
+        );
+    }
+
+    pub fn decrypt(&mut self, buf: &mut [u8]) {
+        cfb128_xcrypt(
+            self.key.size.nr(),
+            &self.key.round_key,
+            &mut self.register,
+            &mut self.offset,
+            buf,
+            true,
+        );
+    }
+}
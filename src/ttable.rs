@@ -0,0 +1,156 @@
+//! AES on 32-bit columns with T-tables, which combine SubBytes, ShiftRows
+//! and MixColumns into four lookups and three xors per column.
+//!
+//! The tables are indexed with secret state bytes, and which cache lines
+//! are loaded can be measured by other code on the same machine, so this
+//! is not constant-time. It is only used if `Backend::TTable` is selected.
+//!
+//! A column is a little-endian u32 with row r in byte r.
+
+use super::{RSBOX, SBOX};
+
+// x * 2 in GF(2^8).
+const fn xtime(x: u32) -> u32 {
+    ((x << 1) ^ (((x >> 7) & 1) * 0x1b)) & 0xff
+}
+
+const fn gf_mul(x: u32, y: u32) -> u32 {
+    let (mut x, mut y, mut out) = (x, y, 0);
+    while y != 0 {
+        if y & 1 != 0 {
+            out ^= x;
+        }
+        x = xtime(x);
+        y >>= 1;
+    }
+    out
+}
+
+// Table t holds the column MixColumns (or InvMixColumns) makes of a byte
+// in row t, after the S-box, so it is table 0 rotated by t bytes.
+const fn tables(sbox: &[u8; 256], coefficients: [u32; 4]) -> [[u32; 256]; 4] {
+    let mut tables = [[0u32; 256]; 4];
+    let mut x = 0;
+    while x < 256 {
+        let s = sbox[x] as u32;
+        let column = gf_mul(s, coefficients[0])
+            | gf_mul(s, coefficients[1]) << 8
+            | gf_mul(s, coefficients[2]) << 16
+            | gf_mul(s, coefficients[3]) << 24;
+        let mut t = 0;
+        while t < 4 {
+            tables[t][x] = column.rotate_left(8 * t as u32);
+            t += 1;
+        }
+        x += 1;
+    }
+    tables
+}
+
+static TE: [[u32; 256]; 4] = tables(&SBOX, [2, 1, 1, 3]);
+static TD: [[u32; 256]; 4] = tables(&RSBOX, [14, 9, 13, 11]);
+
+type State = [u32; 4];
+
+fn load(block: &[u8]) -> State {
+    let mut state = [0u32; 4];
+    for (c, column) in state.iter_mut().zip(block.chunks(4)) {
+        *c = u32::from_le_bytes([column[0], column[1], column[2], column[3]]);
+    }
+    state
+}
+
+fn store(state: &State, block: &mut [u8]) {
+    for (c, column) in state.iter().zip(block.chunks_mut(4)) {
+        column.copy_from_slice(&c.to_le_bytes());
+    }
+}
+
+fn byte(column: u32, row: usize) -> usize {
+    (column >> (8 * row)) as u8 as usize
+}
+
+// Column c of the output takes row r from column c + r of the input, or
+// from column c - r when decrypting.
+fn round(tables: &[[u32; 256]; 4], s: &State, inverse: bool) -> State {
+    let mut out = [0u32; 4];
+    for (c, out) in out.iter_mut().enumerate() {
+        for (r, table) in tables.iter().enumerate() {
+            let src = if inverse { c + 4 - r } else { c + r } % 4;
+            *out ^= table[byte(s[src], r)];
+        }
+    }
+    out
+}
+
+// The last round, which has no MixColumns.
+fn last_round(sbox: &[u8; 256], s: &State, inverse: bool) -> State {
+    let mut out = [0u32; 4];
+    for (c, out) in out.iter_mut().enumerate() {
+        for r in 0..4 {
+            let src = if inverse { c + 4 - r } else { c + r } % 4;
+            *out |= (sbox[byte(s[src], r)] as u32) << (8 * r);
+        }
+    }
+    out
+}
+
+fn add_round_key(state: &mut State, key: &State) {
+    for (s, k) in state.iter_mut().zip(key.iter()) {
+        *s ^= k;
+    }
+}
+
+fn round_keys(nr: usize, round_key: &[u8]) -> [State; 15] {
+    let mut keys = [[0u32; 4]; 15];
+    for (key, bytes) in keys.iter_mut().zip(round_key.chunks(16)).take(nr + 1) {
+        *key = load(bytes);
+    }
+    keys
+}
+
+pub(crate) fn encrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    let nr = nr as usize;
+    let keys = round_keys(nr, round_key);
+    for block in blocks.chunks_mut(16) {
+        let mut state = load(block);
+        add_round_key(&mut state, &keys[0]);
+        for key in &keys[1..nr] {
+            state = round(&TE, &state, false);
+            add_round_key(&mut state, key);
+        }
+        state = last_round(&SBOX, &state, false);
+        add_round_key(&mut state, &keys[nr]);
+        store(&state, block);
+    }
+}
+
+// The equivalent inverse cipher of FIPS 197 5.3.5, with InvMixColumns
+// applied to the middle round keys so they can be added after the
+// combined round.
+pub(crate) fn decrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    let nr = nr as usize;
+    let mut keys = round_keys(nr, round_key);
+    for key in keys[1..nr].iter_mut() {
+        // InvMixColumns of a column is TD after undoing its S-box.
+        for column in key.iter_mut() {
+            let mut mixed = 0;
+            for (r, table) in TD.iter().enumerate() {
+                mixed ^= table[SBOX[byte(*column, r)] as usize];
+            }
+            *column = mixed;
+        }
+    }
+
+    for block in blocks.chunks_mut(16) {
+        let mut state = load(block);
+        add_round_key(&mut state, &keys[nr]);
+        for key in keys[1..nr].iter().rev() {
+            state = round(&TD, &state, true);
+            add_round_key(&mut state, key);
+        }
+        state = last_round(&RSBOX, &state, true);
+        add_round_key(&mut state, &keys[0]);
+        store(&state, block);
+    }
+}
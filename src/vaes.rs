@@ -0,0 +1,160 @@
+//! AES with the VAES instructions, on 512-bit registers of four blocks
+//! if the CPU has AVX-512 and on 256-bit registers of two blocks if not.
+//!
+//! Only independent blocks gain from this, so it is used for ECB, CBC
+//! decryption and the CTR keystream. The 512-bit code takes groups of 16
+//! blocks, the 256-bit code groups of 8 and 4 of what is left, and fewer
+//! than 4 blocks are left to the AES-NI code. The functions here may only
+//! be called once `Backend::Vaes` has been found to be available.
+
+use std::arch::x86_64::*;
+
+use super::aesni;
+
+// The round keys, each broadcast to the four lanes of a register.
+unsafe fn load_keys_512(nr: usize, round_key: &[u8]) -> [__m512i; 15] {
+    let mut keys = [_mm512_setzero_si512(); 15];
+    for (key, bytes) in keys.iter_mut().zip(round_key.chunks(16)).take(nr + 1) {
+        *key = _mm512_broadcast_i32x4(_mm_loadu_si128(bytes.as_ptr() as *const __m128i));
+    }
+    keys
+}
+
+#[target_feature(enable = "aes,avx512f,vaes")]
+unsafe fn encrypt_blocks_512(nr: usize, blocks: &mut [u8], round_key: &[u8]) -> usize {
+    let keys = load_keys_512(nr, round_key);
+
+    let full = blocks.len() - blocks.len() % 256;
+    for group in blocks[..full].chunks_exact_mut(256) {
+        let p = group.as_mut_ptr() as *mut __m512i;
+        let mut b = [_mm512_setzero_si512(); 4];
+        for (i, b) in b.iter_mut().enumerate() {
+            *b = _mm512_xor_si512(_mm512_loadu_si512(p.add(i) as *const _), keys[0]);
+        }
+        for key in &keys[1..nr] {
+            for b in b.iter_mut() {
+                *b = _mm512_aesenc_epi128(*b, *key);
+            }
+        }
+        for (i, b) in b.iter().enumerate() {
+            _mm512_storeu_si512(p.add(i) as *mut _, _mm512_aesenclast_epi128(*b, keys[nr]));
+        }
+    }
+    full
+}
+
+#[target_feature(enable = "aes,avx512f,vaes")]
+unsafe fn decrypt_blocks_512(nr: usize, blocks: &mut [u8], round_key: &[u8]) -> usize {
+    // The equivalent inverse cipher, as in the AES-NI code.
+    let enc = load_keys_512(nr, round_key);
+    let mut keys = [_mm512_setzero_si512(); 15];
+    keys[0] = enc[nr];
+    for i in 1..nr {
+        let key = _mm512_castsi512_si128(enc[nr - i]);
+        keys[i] = _mm512_broadcast_i32x4(_mm_aesimc_si128(key));
+    }
+    keys[nr] = enc[0];
+
+    let full = blocks.len() - blocks.len() % 256;
+    for group in blocks[..full].chunks_exact_mut(256) {
+        let p = group.as_mut_ptr() as *mut __m512i;
+        let mut b = [_mm512_setzero_si512(); 4];
+        for (i, b) in b.iter_mut().enumerate() {
+            *b = _mm512_xor_si512(_mm512_loadu_si512(p.add(i) as *const _), keys[0]);
+        }
+        for key in &keys[1..nr] {
+            for b in b.iter_mut() {
+                *b = _mm512_aesdec_epi128(*b, *key);
+            }
+        }
+        for (i, b) in b.iter().enumerate() {
+            _mm512_storeu_si512(p.add(i) as *mut _, _mm512_aesdeclast_epi128(*b, keys[nr]));
+        }
+    }
+    full
+}
+
+// The round keys, each broadcast to the two lanes of a register.
+unsafe fn load_keys_256(nr: usize, round_key: &[u8]) -> [__m256i; 15] {
+    let mut keys = [_mm256_setzero_si256(); 15];
+    for (key, bytes) in keys.iter_mut().zip(round_key.chunks(16)).take(nr + 1) {
+        *key = _mm256_broadcastsi128_si256(_mm_loadu_si128(bytes.as_ptr() as *const __m128i));
+    }
+    keys
+}
+
+// Groups of 8 blocks in four registers, and a last group of 4 blocks in two
+// registers if that many are left.
+#[target_feature(enable = "aes,avx2,vaes")]
+unsafe fn encrypt_blocks_256(nr: usize, blocks: &mut [u8], round_key: &[u8]) -> usize {
+    let keys = load_keys_256(nr, round_key);
+
+    let full = blocks.len() - blocks.len() % 64;
+    for group in blocks[..full].chunks_mut(128) {
+        let n = group.len() / 32;
+        let p = group.as_mut_ptr() as *mut __m256i;
+        let mut b = [_mm256_setzero_si256(); 4];
+        for (i, b) in b[..n].iter_mut().enumerate() {
+            *b = _mm256_xor_si256(_mm256_loadu_si256(p.add(i)), keys[0]);
+        }
+        for key in &keys[1..nr] {
+            for b in b[..n].iter_mut() {
+                *b = _mm256_aesenc_epi128(*b, *key);
+            }
+        }
+        for (i, b) in b[..n].iter().enumerate() {
+            _mm256_storeu_si256(p.add(i), _mm256_aesenclast_epi128(*b, keys[nr]));
+        }
+    }
+    full
+}
+
+#[target_feature(enable = "aes,avx2,vaes")]
+unsafe fn decrypt_blocks_256(nr: usize, blocks: &mut [u8], round_key: &[u8]) -> usize {
+    let enc = load_keys_256(nr, round_key);
+    let mut keys = [_mm256_setzero_si256(); 15];
+    keys[0] = enc[nr];
+    for i in 1..nr {
+        let key = _mm256_castsi256_si128(enc[nr - i]);
+        keys[i] = _mm256_broadcastsi128_si256(_mm_aesimc_si128(key));
+    }
+    keys[nr] = enc[0];
+
+    let full = blocks.len() - blocks.len() % 64;
+    for group in blocks[..full].chunks_mut(128) {
+        let n = group.len() / 32;
+        let p = group.as_mut_ptr() as *mut __m256i;
+        let mut b = [_mm256_setzero_si256(); 4];
+        for (i, b) in b[..n].iter_mut().enumerate() {
+            *b = _mm256_xor_si256(_mm256_loadu_si256(p.add(i)), keys[0]);
+        }
+        for key in &keys[1..nr] {
+            for b in b[..n].iter_mut() {
+                *b = _mm256_aesdec_epi128(*b, *key);
+            }
+        }
+        for (i, b) in b[..n].iter().enumerate() {
+            _mm256_storeu_si256(p.add(i), _mm256_aesdeclast_epi128(*b, keys[nr]));
+        }
+    }
+    full
+}
+
+// `Backend::Vaes` only promises the 256-bit code, AVX-512 is checked here.
+pub(crate) fn encrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    let mut done = 0;
+    if is_x86_feature_detected!("avx512f") {
+        done = unsafe { encrypt_blocks_512(nr as usize, blocks, round_key) };
+    }
+    done += unsafe { encrypt_blocks_256(nr as usize, &mut blocks[done..], round_key) };
+    aesni::encrypt(nr, &mut blocks[done..], round_key);
+}
+
+pub(crate) fn decrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    let mut done = 0;
+    if is_x86_feature_detected!("avx512f") {
+        done = unsafe { decrypt_blocks_512(nr as usize, blocks, round_key) };
+    }
+    done += unsafe { decrypt_blocks_256(nr as usize, &mut blocks[done..], round_key) };
+    aesni::decrypt(nr, &mut blocks[done..], round_key);
+}
@@ -0,0 +1,304 @@
+//! Constant-time AES with the SSSE3 byte shuffle, after Hamburg,
+//! "Accelerating AES with Vector Permute Instructions" (CHES 2009).
+//!
+//! PSHUFB looks up all 16 bytes of a register in a 16-entry table at once,
+//! so 4-bit functions cost one instruction and no memory access depends on
+//! secret data. The S-box is inverted in GF(2^8) represented as
+//! GF(2^4)[t] / (t^2 + t + 1/a), where an element is i + k t with i in the
+//! low and k in the high nibble. With j = i + k its inverse follows from
+//!
+//! ```text
+//! io = j + 1 / (1/i + a/k)
+//! jo = i + 1 / (1/j + a/k)
+//! ```
+//!
+//! whose inverses are linear in the inverse of i + k t, so the output
+//! tables below map io and jo straight to the S-box value. 1/0 is looked up
+//! as 0x80, for which PSHUFB returns 0, which makes the formulas hold when
+//! any of the terms is 0 as well. The linear layers use shuffles and adds.
+//!
+//! The functions here may only be called once `Backend::Vperm` has been
+//! found to be available.
+
+use std::arch::x86_64::*;
+
+// GF(2^4) with w^4 + w + 1.
+const fn gf16_mul(x: u8, y: u8) -> u8 {
+    let (mut x, mut y, mut out) = (x, y, 0);
+    while y != 0 {
+        if y & 1 != 0 {
+            out ^= x;
+        }
+        x <<= 1;
+        if x & 0x10 != 0 {
+            x ^= 0x13;
+        }
+        y >>= 1;
+    }
+    out
+}
+
+const fn gf16_inv(x: u8) -> u8 {
+    let mut y = 1;
+    while gf16_mul(x, y) != 1 {
+        y += 1;
+    }
+    y
+}
+
+// GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1.
+const fn gf256_mul(x: u8, y: u8) -> u8 {
+    let (mut x, mut y, mut out) = (x, y, 0);
+    while y != 0 {
+        if y & 1 != 0 {
+            out ^= x;
+        }
+        x = (x << 1) ^ (((x >> 7) & 1) * 0x1b);
+        y >>= 1;
+    }
+    out
+}
+
+// The linear part of the S-box affine transformation, and its inverse.
+const fn affine(x: u8) -> u8 {
+    x ^ x.rotate_left(1) ^ x.rotate_left(2) ^ x.rotate_left(3) ^ x.rotate_left(4)
+}
+
+const fn inv_affine(x: u8) -> u8 {
+    x.rotate_left(1) ^ x.rotate_left(3) ^ x.rotate_left(6)
+}
+
+// GF(2^4) in GF(2^8), given the powers of a root of w^4 + w + 1.
+const fn embed(powers: &[u8; 4], x: u8) -> u8 {
+    let (mut out, mut b) = (0, 0);
+    while b < 4 {
+        if (x >> b) & 1 != 0 {
+            out ^= powers[b];
+        }
+        b += 1;
+    }
+    out
+}
+
+// The byte for i + k t, given a root t of t^2 + t + z.
+const fn from_tower(powers: &[u8; 4], t: u8, x: u8) -> u8 {
+    embed(powers, x & 15) ^ gf256_mul(embed(powers, x >> 4), t)
+}
+
+struct Tables {
+    // 1/x and a/x in GF(2^4).
+    inv: [u8; 16],
+    a_inv: [u8; 16],
+    // The change to the tower field from the low and high nibble of a byte,
+    // after undoing the affine transformation for decryption.
+    enc_in: [[u8; 16]; 2],
+    dec_in: [[u8; 16]; 2],
+    // The S-box without its constant, and the inverse S-box, as the sum of
+    // a lookup of io and one of jo.
+    enc_out: [[u8; 16]; 2],
+    dec_out: [[u8; 16]; 2],
+}
+
+const fn tables() -> Tables {
+    // t^2 + t + z is irreducible if z is not y^2 + y for any y.
+    let mut z = 1;
+    loop {
+        let mut y = 0;
+        while y < 16 && gf16_mul(y, y) ^ y != z {
+            y += 1;
+        }
+        if y == 16 {
+            break;
+        }
+        z += 1;
+    }
+    let a = gf16_inv(z);
+
+    // The roots of w^4 + w + 1 and t^2 + t + z in GF(2^8), which give the
+    // tower field element i + k t as a byte.
+    let mut w: u8 = 2;
+    while gf256_mul(gf256_mul(w, w), gf256_mul(w, w)) ^ w ^ 1 != 0 {
+        w += 1;
+    }
+    let mut powers = [1u8; 4];
+    let mut b = 1;
+    while b < 4 {
+        powers[b] = gf256_mul(powers[b - 1], w);
+        b += 1;
+    }
+    let mut t: u8 = 0;
+    while gf256_mul(t, t) ^ t ^ embed(&powers, z) != 0 {
+        t += 1;
+    }
+    let mut to_tower = [0u8; 256];
+    let mut x = 0;
+    while x < 256 {
+        to_tower[from_tower(&powers, t, x as u8) as usize] = x as u8;
+        x += 1;
+    }
+
+    let mut tables = Tables {
+        inv: [0x80; 16],
+        a_inv: [0x80; 16],
+        enc_in: [[0; 16]; 2],
+        dec_in: [[0; 16]; 2],
+        enc_out: [[0; 16]; 2],
+        dec_out: [[0; 16]; 2],
+    };
+    let (p, q) = (gf16_inv(a), 1 ^ gf16_inv(a));
+    let mut n = 0;
+    while n < 16 {
+        if n != 0 {
+            tables.inv[n] = gf16_inv(n as u8);
+            tables.a_inv[n] = gf16_mul(a, gf16_inv(n as u8));
+        }
+        let (lo, hi) = (n as u8, (n as u8) << 4);
+        tables.enc_in[0][n] = to_tower[lo as usize];
+        tables.enc_in[1][n] = to_tower[hi as usize];
+        tables.dec_in[0][n] = to_tower[(inv_affine(lo) ^ inv_affine(0x63)) as usize];
+        tables.dec_in[1][n] = to_tower[inv_affine(hi) as usize];
+
+        // The inverse of i + k t is (1/io) (p + t) + (1/jo) (q + t).
+        let y = if n == 0 { 0 } else { gf16_inv(n as u8) };
+        let io = from_tower(&powers, t, gf16_mul(p, y) | y << 4);
+        let jo = from_tower(&powers, t, gf16_mul(q, y) | y << 4);
+        tables.enc_out[0][n] = affine(io);
+        tables.enc_out[1][n] = affine(jo);
+        tables.dec_out[0][n] = io;
+        tables.dec_out[1][n] = jo;
+        n += 1;
+    }
+    tables
+}
+
+static TABLES: Tables = tables();
+
+// Byte c * 4 + r of a block is row r of column c.
+const SHIFT_ROWS: [u8; 16] = [0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12, 1, 6, 11];
+const INV_SHIFT_ROWS: [u8; 16] = [0, 13, 10, 7, 4, 1, 14, 11, 8, 5, 2, 15, 12, 9, 6, 3];
+// Row r of every column takes the value of row r + 1.
+const ROTATE_ROWS: [u8; 16] = [1, 2, 3, 0, 5, 6, 7, 4, 9, 10, 11, 8, 13, 14, 15, 12];
+
+unsafe fn load(bytes: &[u8]) -> __m128i {
+    _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn lookup(table: &[u8; 16], x: __m128i) -> __m128i {
+    _mm_shuffle_epi8(load(table), x)
+}
+
+// Both output tables applied to the inverse of x in the tower field.
+#[target_feature(enable = "ssse3")]
+unsafe fn invert(x: __m128i, out: &[[u8; 16]; 2]) -> __m128i {
+    let low = _mm_set1_epi8(0x0f);
+    let i = _mm_and_si128(x, low);
+    let k = _mm_and_si128(_mm_srli_epi16(x, 4), low);
+    let j = _mm_xor_si128(i, k);
+    let ak = lookup(&TABLES.a_inv, k);
+    let iak = _mm_xor_si128(lookup(&TABLES.inv, i), ak);
+    let jak = _mm_xor_si128(lookup(&TABLES.inv, j), ak);
+    let io = _mm_xor_si128(j, lookup(&TABLES.inv, iak));
+    let jo = _mm_xor_si128(i, lookup(&TABLES.inv, jak));
+    _mm_xor_si128(lookup(&out[0], io), lookup(&out[1], jo))
+}
+
+// The tables for the two nibbles of every byte of x.
+#[target_feature(enable = "ssse3")]
+unsafe fn transform(x: __m128i, tables: &[[u8; 16]; 2]) -> __m128i {
+    let low = _mm_set1_epi8(0x0f);
+    let lo = _mm_and_si128(x, low);
+    let hi = _mm_and_si128(_mm_srli_epi16(x, 4), low);
+    _mm_xor_si128(lookup(&tables[0], lo), lookup(&tables[1], hi))
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn sub_bytes(x: __m128i) -> __m128i {
+    let y = invert(transform(x, &TABLES.enc_in), &TABLES.enc_out);
+    _mm_xor_si128(y, _mm_set1_epi8(0x63))
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn inv_sub_bytes(x: __m128i) -> __m128i {
+    invert(transform(x, &TABLES.dec_in), &TABLES.dec_out)
+}
+
+// x * 2 in every byte, reducing the bytes that had their top bit set.
+#[target_feature(enable = "ssse3")]
+unsafe fn xtime(x: __m128i) -> __m128i {
+    let carry = _mm_cmplt_epi8(x, _mm_setzero_si128());
+    _mm_xor_si128(
+        _mm_add_epi8(x, x),
+        _mm_and_si128(carry, _mm_set1_epi8(0x1b)),
+    )
+}
+
+// Row r becomes 2 (a_r + a_{r+1}) + a_{r+1} + a_{r+2} + a_{r+3}.
+#[target_feature(enable = "ssse3")]
+unsafe fn mix_columns(x: __m128i) -> __m128i {
+    let rotate = load(&ROTATE_ROWS);
+    let x1 = _mm_shuffle_epi8(x, rotate);
+    let x2 = _mm_shuffle_epi8(x1, rotate);
+    let x3 = _mm_shuffle_epi8(x2, rotate);
+    let t = xtime(_mm_xor_si128(x, x1));
+    _mm_xor_si128(_mm_xor_si128(t, x1), _mm_xor_si128(x2, x3))
+}
+
+// InvMixColumns is MixColumns after adding 4 (a_r + a_{r+2}) to rows r and
+// r + 2.
+#[target_feature(enable = "ssse3")]
+unsafe fn inv_mix_columns(x: __m128i) -> __m128i {
+    let rotate = load(&ROTATE_ROWS);
+    let x2 = _mm_shuffle_epi8(_mm_shuffle_epi8(x, rotate), rotate);
+    let t = xtime(xtime(_mm_xor_si128(x, x2)));
+    mix_columns(_mm_xor_si128(x, t))
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn encrypt_blocks(nr: usize, blocks: &mut [u8], round_key: &[u8]) {
+    let shift_rows = load(&SHIFT_ROWS);
+    for block in blocks.chunks_exact_mut(16) {
+        let mut s = _mm_xor_si128(load(block), load(round_key));
+        for round in 1..=nr {
+            s = sub_bytes(_mm_shuffle_epi8(s, shift_rows));
+            if round != nr {
+                s = mix_columns(s);
+            }
+            s = _mm_xor_si128(s, load(&round_key[round * 16..]));
+        }
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, s);
+    }
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn decrypt_blocks(nr: usize, blocks: &mut [u8], round_key: &[u8]) {
+    let inv_shift_rows = load(&INV_SHIFT_ROWS);
+    for block in blocks.chunks_exact_mut(16) {
+        let mut s = _mm_xor_si128(load(block), load(&round_key[nr * 16..]));
+        for round in (0..nr).rev() {
+            s = inv_sub_bytes(_mm_shuffle_epi8(s, inv_shift_rows));
+            s = _mm_xor_si128(s, load(&round_key[round * 16..]));
+            if round != 0 {
+                s = inv_mix_columns(s);
+            }
+        }
+        _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, s);
+    }
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn sub_word_vperm(word: u32) -> u32 {
+    _mm_cvtsi128_si32(sub_bytes(_mm_cvtsi32_si128(word as i32))) as u32
+}
+
+pub(crate) fn encrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    unsafe { encrypt_blocks(nr as usize, blocks, round_key) }
+}
+
+pub(crate) fn decrypt(nr: u8, blocks: &mut [u8], round_key: &[u8]) {
+    unsafe { decrypt_blocks(nr as usize, blocks, round_key) }
+}
+
+pub(crate) fn sub_word(word: &mut [u8; 4]) {
+    *word = unsafe { sub_word_vperm(u32::from_le_bytes(*word)) }.to_le_bytes();
+}
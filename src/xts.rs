@@ -0,0 +1,156 @@
+//! XTS-AES, as specified in IEEE 1619 and NIST SP 800-38E.
+
+use super::{cipher, constant_time_eq, inv_cipher, Error, Mode, Size, AES};
+
+/// A pair of AES contexts: Key1 encrypts the data, Key2 encrypts the tweak.
+pub struct XTS {
+    pub data: AES,
+    pub tweak: AES,
+}
+
+impl XTS {
+    /// Creates a context, panicking if a key has the wrong length.
+    pub fn new(size: Size, key1: &[u8], key2: &[u8]) -> XTS {
+        match XTS::try_new(size, key1, key2) {
+            Ok(xts) => xts,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Creates a context, returning `Error::BadKeyLength` unless both keys
+    /// have the length of `size`. XTS is only defined for AES-128 and
+    /// AES-256, and SP 800-38E requires rejecting equal keys, so those are
+    /// `Error::BadKeyLength` as well.
+    pub fn try_new(size: Size, key1: &[u8], key2: &[u8]) -> Result<XTS, Error> {
+        if size == Size::AES192 || constant_time_eq(key1, key2) {
+            return Err(Error::BadKeyLength);
+        }
+        Ok(XTS {
+            data: AES::try_new(size, Mode::XTS, key1, &[0u8; 16])?,
+            tweak: AES::try_new(size, Mode::XTS, key2, &[0u8; 16])?,
+        })
+    }
+}
+
+// Multiplies the tweak by the primitive element alpha of GF(2^128).
+// The tweak is stored little endian, so the carry out of the last byte is
+// folded back into the first one.
+fn mul_alpha(t: &mut [u8; 16]) {
+    let mut carry = 0u8;
+    for b in t.iter_mut() {
+        let next = *b >> 7;
+        *b = (*b << 1) | carry;
+        carry = next;
+    }
+    t[0] ^= 0x87 & 0u8.wrapping_sub(carry);
+}
+
+fn xor_block(block: &mut [u8], t: &[u8; 16]) {
+    for (b, k) in block.iter_mut().zip(t.iter()) {
+        *b ^= k;
+    }
+}
+
+fn encrypt_block(ctx: &AES, block: &mut [u8], t: &[u8; 16]) {
+    xor_block(block, t);
+    cipher(ctx.nr(), block, &ctx.round_key);
+    xor_block(block, t);
+}
+
+fn decrypt_block(ctx: &AES, block: &mut [u8], t: &[u8; 16]) {
+    xor_block(block, t);
+    inv_cipher(ctx.nr(), block, &ctx.round_key);
+    xor_block(block, t);
+}
+
+// Encodes a sector number as the little endian 128 bit tweak value.
+fn sector_tweak(sector: u64) -> [u8; 16] {
+    let mut tweak = [0u8; 16];
+    tweak[..8].copy_from_slice(&sector.to_le_bytes());
+    tweak
+}
+
+// A data unit needs at least one full block to steal from.
+fn check_params(buf: &[u8]) -> Result<(), Error> {
+    if buf.len() < 16 {
+        return Err(Error::MisalignedBuffer);
+    }
+    Ok(())
+}
+
+/// Encrypts one data unit in place.
+///
+/// `buf` must be at least 16 bytes long, otherwise `Error::MisalignedBuffer`
+/// is returned. A trailing partial block is handled with ciphertext
+/// stealing, so the output has the same length.
+pub fn aes_xts_encrypt(ctx: &XTS, tweak: &[u8; 16], buf: &mut [u8]) -> Result<(), Error> {
+    check_params(buf)?;
+
+    let mut t = *tweak;
+    cipher(ctx.tweak.nr(), &mut t, &ctx.tweak.round_key);
+
+    let tail = buf.len() % 16;
+    let full = buf.len() - tail;
+    for block in buf[..full].chunks_mut(16) {
+        encrypt_block(&ctx.data, block, &t);
+        mul_alpha(&mut t);
+    }
+
+    if tail != 0 {
+        // Steal the end of the last full ciphertext block to pad the
+        // partial block, and swap the two.
+        let (head, last) = buf.split_at_mut(full);
+        let prev = &mut head[full - 16..];
+        let mut pp = [0u8; 16];
+        pp[..tail].copy_from_slice(last);
+        pp[tail..].copy_from_slice(&prev[tail..]);
+        last.copy_from_slice(&prev[..tail]);
+        encrypt_block(&ctx.data, &mut pp, &t);
+        prev.copy_from_slice(&pp);
+    }
+    Ok(())
+}
+
+/// Decrypts one data unit in place, reversing `aes_xts_encrypt`.
+pub fn aes_xts_decrypt(ctx: &XTS, tweak: &[u8; 16], buf: &mut [u8]) -> Result<(), Error> {
+    check_params(buf)?;
+
+    let mut t = *tweak;
+    cipher(ctx.tweak.nr(), &mut t, &ctx.tweak.round_key);
+
+    let tail = buf.len() % 16;
+    let full = buf.len() - tail;
+    // With stealing, the last full block is decrypted with the tweak of
+    // the partial block, so it is left for the end.
+    let blocks = if tail == 0 { full } else { full - 16 };
+    for block in buf[..blocks].chunks_mut(16) {
+        decrypt_block(&ctx.data, block, &t);
+        mul_alpha(&mut t);
+    }
+
+    if tail != 0 {
+        let mut next = t;
+        mul_alpha(&mut next);
+
+        let (head, last) = buf.split_at_mut(full);
+        let prev = &mut head[full - 16..];
+        decrypt_block(&ctx.data, prev, &next);
+        let mut cc = [0u8; 16];
+        cc[..tail].copy_from_slice(last);
+        cc[tail..].copy_from_slice(&prev[tail..]);
+        last.copy_from_slice(&prev[..tail]);
+        decrypt_block(&ctx.data, &mut cc, &t);
+        prev.copy_from_slice(&cc);
+    }
+    Ok(())
+}
+
+/// Encrypts the sector with the given number, used as the tweak.
+pub fn aes_xts_encrypt_sector(ctx: &XTS, sector: u64, buf: &mut [u8]) -> Result<(), Error> {
+    aes_xts_encrypt(ctx, &sector_tweak(sector), buf)
+}
+
+/// Decrypts the sector with the given number, used as the tweak.
+pub fn aes_xts_decrypt_sector(ctx: &XTS, sector: u64, buf: &mut [u8]) -> Result<(), Error> {
+    aes_xts_decrypt(ctx, &sector_tweak(sector), buf)
+}
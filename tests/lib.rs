@@ -1,5 +1,6 @@
 //! Tests are based on
 //! https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf Appendix F
+//! and the test vectors of the specifications of the other modes.
 
 extern crate aes;
 extern crate data_encoding;
@@ -61,15 +62,79 @@ fn ecb_aes_128_encrypt() {
         let mut input = as_vec(block[0]);
         let output = as_vec(block[3]);
 
-        let mut out = input.as_mut_slice();
-        aes_ecb_encrypt(&aes, out);
+        let out = input.as_mut_slice();
+        aes_ecb_encrypt(&aes, out).unwrap();
         assert_eq!(out, output.as_slice());
 
-        aes_ecb_decrypt(&aes, out);
+        aes_ecb_decrypt(&aes, out).unwrap();
         assert_eq!(out, as_vec(block[0]).as_slice());
     }
 }
 
+// F.1.1 - F.1.6   ECB-AES128/192/256.Encrypt and .Decrypt, all four blocks
+// at once.
+#[test]
+fn ecb_aes_multi_block() {
+    let plain = as_vec(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+    let vectors = [
+        (
+            Size::AES128,
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf\
+             43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4",
+        ),
+        (
+            Size::AES192,
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "bd334f1d6e45f25ff712a214571fa5cc974104846d0ad3ad7734ecb3ecee4eef\
+             ef7afd2270e2e60adce0ba2face6444e9a4b41ba738d6c72fb16691603c18e0e",
+        ),
+        (
+            Size::AES256,
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "f3eed1bdb5d2a03c064b5a7e3db181f8591ccb10d410ed26dc5ba74a31362870\
+             b6ed21b99ca6f4f9f153e7b1beafed1d23304b7a39f9f3ff067d8d8f9e24ecc7",
+        ),
+    ];
+
+    for &(size, key, cipher) in vectors.iter() {
+        let aes = AES::new(size, Mode::ECB, as_vec(key).as_slice(), &[0u8; 16]);
+
+        let mut buf = plain.clone();
+        aes_ecb_encrypt(&aes, &mut buf).unwrap();
+        assert_eq!(buf, as_vec(cipher));
+        aes_ecb_decrypt(&aes, &mut buf).unwrap();
+        assert_eq!(buf, plain);
+
+        let mut out = vec![0u8; plain.len()];
+        aes_ecb_encrypt_to(&aes, &plain, &mut out).unwrap();
+        assert_eq!(out, as_vec(cipher));
+        aes_ecb_decrypt_to(&aes, &as_vec(cipher), &mut out).unwrap();
+        assert_eq!(out, plain);
+    }
+}
+
+#[test]
+fn ecb_rejects_misaligned_buffer() {
+    let aes = AES::new(Size::AES128, Mode::ECB, &[0u8; 16], &[0u8; 16]);
+
+    let mut buf = [0u8; 17];
+    assert_eq!(aes_ecb_encrypt(&aes, &mut buf), Err(Error::MisalignedBuffer));
+    assert_eq!(aes_ecb_decrypt(&aes, &mut buf[..15]), Err(Error::MisalignedBuffer));
+    assert_eq!(buf, [0u8; 17]);
+
+    let mut out = [0u8; 17];
+    assert_eq!(
+        aes_ecb_encrypt_to(&aes, &buf, &mut out),
+        Err(Error::MisalignedBuffer)
+    );
+
+    aes_ecb_encrypt(&aes, &mut []).unwrap();
+}
+
 // F.2.1       CBC-AES128.Encrypt
 #[test]
 fn cbc_aes_128_encrypt() {
@@ -118,8 +183,8 @@ fn cbc_aes_128_encrypt() {
         let mut input = as_vec(block[0]);
         let output = as_vec(block[3]);
 
-        let mut out = input.as_mut_slice();
-        aes_cbc_encrypt_buffer(&mut aes, out);
+        let out = input.as_mut_slice();
+        aes_cbc_encrypt_buffer(&mut aes, out).unwrap();
         assert_eq!(out, output.as_slice());
     }
 }
@@ -172,8 +237,8 @@ fn cbc_aes_128_decrypt() {
         let mut input = as_vec(block[0]);
         let output = as_vec(block[3]);
 
-        let mut out = input.as_mut_slice();
-        aes_cbc_decrypt_buffer(&mut aes, out);
+        let out = input.as_mut_slice();
+        aes_cbc_decrypt_buffer(&mut aes, out).unwrap();
         assert_eq!(out, output.as_slice());
     }
 }
@@ -226,8 +291,8 @@ fn ctr_aes_128_encrypt() {
         let mut input = as_vec(block[0]);
         let output = as_vec(block[3]);
 
-        let mut out = input.as_mut_slice();
-        aes_ctr_xcrypt_buffer(&mut aes, out);
+        let out = input.as_mut_slice();
+        aes_ctr_xcrypt_buffer(&mut aes, out).unwrap();
         assert_eq!(out, output.as_slice());
     }
 }
@@ -280,8 +345,2824 @@ fn ctr_aes_128_decrypt() {
         let mut input = as_vec(block[0]);
         let output = as_vec(block[3]);
 
-        let mut out = input.as_mut_slice();
-        aes_ctr_xcrypt_buffer(&mut aes, out);
+        let out = input.as_mut_slice();
+        aes_ctr_xcrypt_buffer(&mut aes, out).unwrap();
         assert_eq!(out, output.as_slice());
     }
 }
+
+// FIPS 197 Appendix C.2   AES-192
+#[test]
+fn ecb_aes_192_encrypt() {
+    let key = as_vec("000102030405060708090a0b0c0d0e0f1011121314151617");
+    let aes = AES::new(Size::AES192, Mode::ECB, key.as_slice(), &[0u8; 16]);
+
+    let mut buf = as_vec("00112233445566778899aabbccddeeff");
+    aes_ecb_encrypt(&aes, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("dda97ca4864cdfe06eaf70a0ec0d7191"));
+
+    aes_ecb_decrypt(&aes, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("00112233445566778899aabbccddeeff"));
+}
+
+// Each vector is [K, P, A, IV, C, T].
+fn check_gcm(size: Size, vectors: &[[&str; 6]]) {
+    for v in vectors.iter() {
+        let aes = AES::new(size, Mode::GCM, as_vec(v[0]).as_slice(), &[0u8; 16]);
+        let aad = as_vec(v[2]);
+        let iv = as_vec(v[3]);
+
+        let mut buf = as_vec(v[1]);
+        let mut tag = [0u8; 16];
+        aes_gcm_seal(&aes, &iv, &aad, &mut buf, &mut tag).unwrap();
+        assert_eq!(buf, as_vec(v[4]));
+        assert_eq!(&tag[..], as_vec(v[5]).as_slice());
+
+        aes_gcm_open(&aes, &iv, &aad, &mut buf, &tag).unwrap();
+        assert_eq!(buf, as_vec(v[1]));
+    }
+}
+
+// GCM-AES128, McGrew & Viega Test Cases 1-6
+#[test]
+fn gcm_aes_128() {
+    let vectors = [
+        [
+            // Test Case 1
+            "00000000000000000000000000000000",
+            "",
+            "",
+            "000000000000000000000000",
+            "",
+            "58e2fccefa7e3061367f1d57a4e7455a",
+        ],
+        [
+            // Test Case 2
+            "00000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "",
+            "000000000000000000000000",
+            "0388dace60b6a392f328c2b971b2fe78",
+            "ab6e47d42cec13bdf53a67b21257bddf",
+        ],
+        [
+            // Test Case 3
+            "feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            "",
+            "cafebabefacedbaddecaf888",
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+            "4d5c2af327cd64a62cf35abd2ba6fab4",
+        ],
+        [
+            // Test Case 4
+            "feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "cafebabefacedbaddecaf888",
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+            "5bc94fbc3221a5db94fae95ae7121a47",
+        ],
+        [
+            // Test Case 5
+            "feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "cafebabefacedbad",
+            "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c742373806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+            "3612d2e79e3b0785561be14aaca2fccb",
+        ],
+        [
+            // Test Case 6
+            "feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca701e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+            "619cc5aefffe0bfa462af43c1699d050",
+        ],
+    ];
+
+    check_gcm(Size::AES128, &vectors);
+}
+
+// GCM-AES192, McGrew & Viega Test Cases 7-12
+#[test]
+fn gcm_aes_192() {
+    let vectors = [
+        [
+            // Test Case 7
+            "000000000000000000000000000000000000000000000000",
+            "",
+            "",
+            "000000000000000000000000",
+            "",
+            "cd33b28ac773f74ba00ed1f312572435",
+        ],
+        [
+            // Test Case 8
+            "000000000000000000000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "",
+            "000000000000000000000000",
+            "98e7247c07f0fe411c267e4384b0f600",
+            "2ff58d80033927ab8ef4d4587514f0fb",
+        ],
+        [
+            // Test Case 9
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            "",
+            "cafebabefacedbaddecaf888",
+            "3980ca0b3c00e841eb06fac4872a2757859e1ceaa6efd984628593b40ca1e19c7d773d00c144c525ac619d18c84a3f4718e2448b2fe324d9ccda2710acade256",
+            "9924a7c8587336bfb118024db8674a14",
+        ],
+        [
+            // Test Case 10
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "cafebabefacedbaddecaf888",
+            "3980ca0b3c00e841eb06fac4872a2757859e1ceaa6efd984628593b40ca1e19c7d773d00c144c525ac619d18c84a3f4718e2448b2fe324d9ccda2710",
+            "2519498e80f1478f37ba55bd6d27618c",
+        ],
+        [
+            // Test Case 11
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "cafebabefacedbad",
+            "0f10f599ae14a154ed24b36e25324db8c566632ef2bbb34f8347280fc4507057fddc29df9a471f75c66541d4d4dad1c9e93a19a58e8b473fa0f062f7",
+            "65dcc57fcf623a24094fcca40d3533f8",
+        ],
+        [
+            // Test Case 12
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            "d27e88681ce3243c4830165a8fdcf9ff1de9a1d8e6b447ef6ef7b79828666e4581e79012af34ddd9e2f037589b292db3e67c036745fa22e7e9b7373b",
+            "dcf566ff291c25bbb8568fc3d376a6d9",
+        ],
+    ];
+
+    check_gcm(Size::AES192, &vectors);
+}
+
+// GCM-AES256, McGrew & Viega Test Cases 13-18
+#[test]
+fn gcm_aes_256() {
+    let vectors = [
+        [
+            // Test Case 13
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "",
+            "",
+            "000000000000000000000000",
+            "",
+            "530f8afbc74536b9a963b4f1c4cb738b",
+        ],
+        [
+            // Test Case 14
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "",
+            "000000000000000000000000",
+            "cea7403d4d606b6e074ec5d3baf39d18",
+            "d0d1c8a799996bf0265b98b5d48ab919",
+        ],
+        [
+            // Test Case 15
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+            "",
+            "cafebabefacedbaddecaf888",
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad",
+            "b094dac5d93471bdec1a502270e3cc6c",
+        ],
+        [
+            // Test Case 16
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "cafebabefacedbaddecaf888",
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662",
+            "76fc6ece0f4e1768cddf8853bb2d551b",
+        ],
+        [
+            // Test Case 17
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "cafebabefacedbad",
+            "c3762df1ca787d32ae47c13bf19844cbaf1ae14d0b976afac52ff7d79bba9de0feb582d33934a4f0954cc2363bc73f7862ac430e64abe499f47c9b1f",
+            "3a337dbf46a792c45e454913fe2ea8f2",
+        ],
+        [
+            // Test Case 18
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            "5a8def2f0c9e53f1f75d7853659e2a20eeb2b22aafde6419a058ab4f6f746bf40fc0c3b780f244452da3ebf1c5d82cdea2418997200ef82e44ae7e3f",
+            "a44a8266ee1c8eb0c8b5d4cf5ae9f19a",
+        ],
+    ];
+
+    check_gcm(Size::AES256, &vectors);
+}
+
+#[test]
+fn gcm_truncated_tag() {
+    // Test Case 4 with the tag truncated to 96 bits.
+    let aes = AES::new(
+        Size::AES128,
+        Mode::GCM,
+        as_vec("feffe9928665731c6d6a8f9467308308").as_slice(),
+        &[0u8; 16],
+    );
+    let iv = as_vec("cafebabefacedbaddecaf888");
+    let aad = as_vec("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let plain = as_vec(
+        "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+         1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+    );
+
+    let mut buf = plain.clone();
+    let mut tag = [0u8; 12];
+    aes_gcm_seal(&aes, &iv, &aad, &mut buf, &mut tag).unwrap();
+    assert_eq!(&tag[..], as_vec("5bc94fbc3221a5db94fae95a").as_slice());
+
+    aes_gcm_open(&aes, &iv, &aad, &mut buf, &tag).unwrap();
+    assert_eq!(buf, plain);
+}
+
+#[test]
+fn gcm_rejects_modified_message() {
+    let aes = AES::new(Size::AES128, Mode::GCM, &[0u8; 16], &[0u8; 16]);
+    let iv = [0u8; 12];
+    let plain = as_vec("00000000000000000000000000000000");
+
+    let mut buf = plain.clone();
+    let mut tag = [0u8; 16];
+    aes_gcm_seal(&aes, &iv, b"header", &mut buf, &mut tag).unwrap();
+    let sealed = buf.clone();
+
+    buf[3] ^= 1;
+    assert_eq!(
+        aes_gcm_open(&aes, &iv, b"header", &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+    buf[3] ^= 1;
+    assert_eq!(
+        aes_gcm_open(&aes, &iv, b"Header", &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+    // The ciphertext is not decrypted when verification fails.
+    assert_eq!(buf, sealed);
+
+    tag[15] ^= 0x80;
+    assert_eq!(
+        aes_gcm_open(&aes, &iv, b"header", &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+}
+
+// Each vector is [N, A, P, C || T].
+fn check_ccm(key: &str, tag_len: usize, vectors: &[[&str; 4]]) {
+    let aes = AES::new(Size::AES128, Mode::CCM, as_vec(key).as_slice(), &[0u8; 16]);
+    for v in vectors.iter() {
+        let nonce = as_vec(v[0]);
+        let aad = as_vec(v[1]);
+        let expected = as_vec(v[3]);
+        let (ciphertext, expected_tag) = expected.split_at(expected.len() - tag_len);
+
+        let mut buf = as_vec(v[2]);
+        let mut tag = vec![0u8; tag_len];
+        aes_ccm_seal(&aes, &nonce, &aad, &mut buf, &mut tag).unwrap();
+        assert_eq!(buf.as_slice(), ciphertext);
+        assert_eq!(tag.as_slice(), expected_tag);
+
+        aes_ccm_open(&aes, &nonce, &aad, &mut buf, &tag).unwrap();
+        assert_eq!(buf, as_vec(v[2]));
+    }
+}
+
+// RFC 3610 Packet Vectors #1 to #6
+#[test]
+fn ccm_rfc3610_tag_8() {
+    let vectors = [
+        [
+            // Packet Vector #1
+            "00000003020100a0a1a2a3a4a5",
+            "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e",
+            "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0",
+        ],
+        [
+            // Packet Vector #2
+            "00000004030201a0a1a2a3a4a5",
+            "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3ba091d56e10400916",
+        ],
+        [
+            // Packet Vector #3
+            "00000005040302a0a1a2a3a4a5",
+            "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20",
+            "51b1e5f44a197d1da46b0f8e2d282ae871e838bb64da8596574adaa76fbd9fb0c5",
+        ],
+        [
+            // Packet Vector #4
+            "00000006050403a0a1a2a3a4a5",
+            "000102030405060708090a0b",
+            "0c0d0e0f101112131415161718191a1b1c1d1e",
+            "a28c6865939a9a79faaa5c4c2a9d4a91cdac8c96c861b9c9e61ef1",
+        ],
+        [
+            // Packet Vector #5
+            "00000007060504a0a1a2a3a4a5",
+            "000102030405060708090a0b",
+            "0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "dcf1fb7b5d9e23fb9d4e131253658ad86ebdca3e51e83f077d9c2d93",
+        ],
+        [
+            // Packet Vector #6
+            "00000008070605a0a1a2a3a4a5",
+            "000102030405060708090a0b",
+            "0c0d0e0f101112131415161718191a1b1c1d1e1f20",
+            "6fc1b011f006568b5171a42d953d469b2570a4bd87405a0443ac91cb94",
+        ],
+    ];
+
+    check_ccm("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf", 8, &vectors);
+}
+
+// RFC 3610 Packet Vectors #7 to #12
+#[test]
+fn ccm_rfc3610_tag_10() {
+    let vectors = [
+        [
+            // Packet Vector #7
+            "00000009080706a0a1a2a3a4a5",
+            "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e",
+            "0135d1b2c95f41d5d1d4fec185d166b8094e999dfed96c048c56602c97acbb7490",
+        ],
+        [
+            // Packet Vector #8
+            "0000000a090807a0a1a2a3a4a5",
+            "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "7b75399ac0831dd2f0bbd75879a2fd8f6cae6b6cd9b7db24c17b4433f434963f34b4",
+        ],
+        [
+            // Packet Vector #9
+            "0000000b0a0908a0a1a2a3a4a5",
+            "0001020304050607",
+            "08090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20",
+            "82531a60cc24945a4b8279181ab5c84df21ce7f9b73f42e197ea9c07e56b5eb17e5f4e",
+        ],
+        [
+            // Packet Vector #10
+            "0000000c0b0a09a0a1a2a3a4a5",
+            "000102030405060708090a0b",
+            "0c0d0e0f101112131415161718191a1b1c1d1e",
+            "07342594157785152b074098330abb141b947b566aa9406b4d999988dd",
+        ],
+        [
+            // Packet Vector #11
+            "0000000d0c0b0aa0a1a2a3a4a5",
+            "000102030405060708090a0b",
+            "0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "676bb20380b0e301e8ab79590a396da78b834934f53aa2e9107a8b6c022c",
+        ],
+        [
+            // Packet Vector #12
+            "0000000e0d0c0ba0a1a2a3a4a5",
+            "000102030405060708090a0b",
+            "0c0d0e0f101112131415161718191a1b1c1d1e1f20",
+            "c0ffa0d6f05bdb67f24d43a4338d2aa4bed7b20e43cd1aa31662e7ad65d6db",
+        ],
+    ];
+
+    check_ccm("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf", 10, &vectors);
+}
+
+// SP 800-38C Appendix C, Examples 1 to 3
+#[test]
+fn ccm_sp800_38c() {
+    let key = "404142434445464748494a4b4c4d4e4f";
+
+    check_ccm(
+        key,
+        4,
+        &[
+            [
+                "10111213141516",
+                "0001020304050607",
+                "20212223",
+                "7162015b4dac255d",
+            ],
+        ],
+    );
+    check_ccm(
+        key,
+        6,
+        &[
+            [
+                "1011121314151617",
+                "000102030405060708090a0b0c0d0e0f",
+                "202122232425262728292a2b2c2d2e2f",
+                "d2a1f0e051ea5f62081a7792073d593d1fc64fbfaccd",
+            ],
+        ],
+    );
+    check_ccm(
+        key,
+        8,
+        &[
+            [
+                "101112131415161718191a1b",
+                "000102030405060708090a0b0c0d0e0f10111213",
+                "202122232425262728292a2b2c2d2e2f3031323334353637",
+                "e3b201a9f5b71a7a9b1ceaeccd97e70b6176aad9a4428aa5484392fbc1b09951",
+            ],
+        ],
+    );
+}
+
+// SP 800-38C Appendix C, Example 4: 2^16 bytes of associated data
+#[test]
+fn ccm_sp800_38c_long_aad() {
+    let aes = AES::new(
+        Size::AES128,
+        Mode::CCM,
+        as_vec("404142434445464748494a4b4c4d4e4f").as_slice(),
+        &[0u8; 16],
+    );
+    let nonce = as_vec("101112131415161718191a1b1c");
+    let aad: Vec<u8> = (0..65536).map(|i| i as u8).collect();
+    let plain = as_vec("202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f");
+
+    let mut buf = plain.clone();
+    let mut tag = [0u8; 14];
+    aes_ccm_seal(&aes, &nonce, &aad, &mut buf, &mut tag).unwrap();
+    assert_eq!(
+        buf,
+        as_vec("69915dad1e84c6376a68c2967e4dab615ae0fd1faec44cc484828529463ccf72"),
+    );
+    assert_eq!(&tag[..], as_vec("b4ac6bec93e8598e7f0dadbcea5b").as_slice());
+}
+
+#[test]
+fn ccm_rejects_modified_message() {
+    let aes = AES::new(Size::AES128, Mode::CCM, &[7u8; 16], &[0u8; 16]);
+    let nonce = [1u8; 13];
+    let mut buf = b"attack at dawn".to_vec();
+    let mut tag = [0u8; 8];
+    aes_ccm_seal(&aes, &nonce, b"hdr", &mut buf, &mut tag).unwrap();
+
+    buf[0] ^= 1;
+    let sealed = buf.clone();
+    assert_eq!(
+        aes_ccm_open(&aes, &nonce, b"hdr", &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+    // No plaintext is released on failure.
+    assert_eq!(buf, sealed);
+}
+
+fn check_xts(size: Size, key1: &str, key2: &str, sector: u64, plain: &str, cipher: &str) {
+    let xts = XTS::new(size, as_vec(key1).as_slice(), as_vec(key2).as_slice());
+
+    let mut buf = as_vec(plain);
+    aes_xts_encrypt_sector(&xts, sector, &mut buf).unwrap();
+    assert_eq!(buf, as_vec(cipher));
+
+    aes_xts_decrypt_sector(&xts, sector, &mut buf).unwrap();
+    assert_eq!(buf, as_vec(plain));
+}
+
+// IEEE 1619 Annex B, XTS-AES-128 Vectors 2 to 4. Vector 1 uses the same
+// key twice, which SP 800-38E does not allow.
+#[test]
+fn xts_aes_128() {
+    // Vector 2
+    check_xts(
+        Size::AES128,
+        "11111111111111111111111111111111",
+        "22222222222222222222222222222222",
+        0x3333333333,
+        "4444444444444444444444444444444444444444444444444444444444444444",
+        "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+    );
+
+    // Vector 3
+    check_xts(
+        Size::AES128,
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+        "22222222222222222222222222222222",
+        0x3333333333,
+        "4444444444444444444444444444444444444444444444444444444444444444",
+        "af85336b597afc1a900b2eb21ec949d292df4c047e0b21532186a5971a227a89",
+    );
+
+    // Vector 4
+    check_xts(
+        Size::AES128,
+        "27182818284590452353602874713526",
+        "31415926535897932384626433832795",
+        0x0,
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+         202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+         404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+         606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f\
+         808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+         a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebf\
+         c0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+         e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff\
+         000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+         202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+         404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+         606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f\
+         808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+         a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebf\
+         c0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+         e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+        "27a7479befa1d476489f308cd4cfa6e2a96e4bbe3208ff25287dd3819616e89c\
+         c78cf7f5e543445f8333d8fa7f56000005279fa5d8b5e4ad40e736ddb4d35412\
+         328063fd2aab53e5ea1e0a9f332500a5df9487d07a5c92cc512c8866c7e860ce\
+         93fdf166a24912b422976146ae20ce846bb7dc9ba94a767aaef20c0d61ad0265\
+         5ea92dc4c4e41a8952c651d33174be51a10c421110e6d81588ede82103a252d8\
+         a750e8768defffed9122810aaeb99f9172af82b604dc4b8e51bcb08235a6f434\
+         1332e4ca60482a4ba1a03b3e65008fc5da76b70bf1690db4eae29c5f1badd03c\
+         5ccf2a55d705ddcd86d449511ceb7ec30bf12b1fa35b913f9f747a8afd1b130e\
+         94bff94effd01a91735ca1726acd0b197c4e5b03393697e126826fb6bbde8ecc\
+         1e08298516e2c9ed03ff3c1b7860f6de76d4cecd94c8119855ef5297ca67e9f3\
+         e7ff72b1e99785ca0a7e7720c5b36dc6d72cac9574c8cbbc2f801e23e56fd344\
+         b07f22154beba0f08ce8891e643ed995c94d9a69c9f1b5f499027a78572aeebd\
+         74d20cc39881c213ee770b1010e4bea718846977ae119f7a023ab58cca0ad752\
+         afe656bb3c17256a9f6e9bf19fdd5a38fc82bbe872c5539edb609ef4f79c203e\
+         bb140f2e583cb2ad15b4aa5b655016a8449277dbd477ef2c8d6c017db738b18d\
+         eb4a427d1923ce3ff262735779a418f20a282df920147beabe421ee5319d0568",
+    );
+}
+
+// IEEE 1619 Annex B, XTS-AES-128 Vectors 15 to 18 (ciphertext stealing)
+#[test]
+fn xts_aes_128_stealing() {
+    // Vector 15
+    check_xts(
+        Size::AES128,
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+        "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+        0x123456789a,
+        "000102030405060708090a0b0c0d0e0f10",
+        "6c1625db4671522d3d7599601de7ca09ed",
+    );
+
+    // Vector 16
+    check_xts(
+        Size::AES128,
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+        "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+        0x123456789a,
+        "000102030405060708090a0b0c0d0e0f1011",
+        "d069444b7a7e0cab09e24447d24deb1fedbf",
+    );
+
+    // Vector 17
+    check_xts(
+        Size::AES128,
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+        "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+        0x123456789a,
+        "000102030405060708090a0b0c0d0e0f101112",
+        "e5df1351c0544ba1350b3363cd8ef4beedbf9d",
+    );
+
+    // Vector 18
+    check_xts(
+        Size::AES128,
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+        "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+        0x123456789a,
+        "000102030405060708090a0b0c0d0e0f10111213",
+        "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac",
+    );
+}
+
+// IEEE 1619 Annex B, XTS-AES-128 Vector 19
+#[test]
+fn xts_aes_128_vector_19() {
+    // Vector 19
+    check_xts(
+        Size::AES128,
+        "e0e1e2e3e4e5e6e7e8e9eaebecedeeef",
+        "c0c1c2c3c4c5c6c7c8c9cacbcccdcecf",
+        0xa987654321,
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+         202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+         404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+         606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f\
+         808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+         a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebf\
+         c0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+         e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff\
+         000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+         202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+         404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+         606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f\
+         808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+         a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebf\
+         c0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+         e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+        "38b45812ef43a05bd957e545907e223b954ab4aaf088303ad910eadf14b42be6\
+         8b2461149d8c8ba85f992be970bc621f1b06573f63e867bf5875acafa04e42cc\
+         bd7bd3c2a0fb1fff791ec5ec36c66ae4ac1e806d81fbf709dbe29e471fad3854\
+         9c8e66f5345d7c1eb94f405d1ec785cc6f6a68f6254dd8339f9d84057e01a177\
+         41990482999516b5611a38f41bb6478e6f173f320805dd71b1932fc333cb9ee3\
+         9936beea9ad96fa10fb4112b901734ddad40bc1878995f8e11aee7d141a2f5d4\
+         8b7a4e1e7f0b2c04830e69a4fd1378411c2f287edf48c6c4e5c247a19680f7fe\
+         41cefbd49b582106e3616cbbe4dfb2344b2ae9519391f3e0fb4922254b1d6d2d\
+         19c6d4d537b3a26f3bcc51588b32f3eca0829b6a5ac72578fb814fb43cf80d64\
+         a233e3f997a3f02683342f2b33d25b492536b93becb2f5e1a8b82f5b88334272\
+         9e8ae09d16938841a21a97fb543eea3bbff59f13c1a18449e398701c1ad51648\
+         346cbc04c27bb2da3b93a1372ccae548fb53bee476f9e9c91773b1bb19828394\
+         d55d3e1a20ed69113a860b6829ffa847224604435070221b257e8dff783615d2\
+         cae4803a93aa4334ab482a0afac9c0aeda70b45a481df5dec5df8cc0f423c77a\
+         5fd46cd312021d4b438862419a791be03bb4d97c0e59578542531ba466a83baf\
+         92cefc151b5cc1611a167893819b63fb8a6b18e86de60290fa72b797b0ce59f3",
+    );
+}
+
+// IEEE 1619 Annex B, XTS-AES-256 Vector 10
+#[test]
+fn xts_aes_256() {
+    // Vector 10
+    check_xts(
+        Size::AES256,
+        "2718281828459045235360287471352662497757247093699959574966967627",
+        "3141592653589793238462643383279502884197169399375105820974944592",
+        0xff,
+        "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+         202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+         404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+         606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f\
+         808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+         a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebf\
+         c0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+         e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff\
+         000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+         202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+         404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+         606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f\
+         808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f\
+         a0a1a2a3a4a5a6a7a8a9aaabacadaeafb0b1b2b3b4b5b6b7b8b9babbbcbdbebf\
+         c0c1c2c3c4c5c6c7c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedf\
+         e0e1e2e3e4e5e6e7e8e9eaebecedeeeff0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+        "1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b\
+         5d31e276f8fe4a8d66b317f9ac683f44680a86ac35adfc3345befecb4bb188fd\
+         5776926c49a3095eb108fd1098baec70aaa66999a72a82f27d848b21d4a741b0\
+         c5cd4d5fff9dac89aeba122961d03a757123e9870f8acf1000020887891429ca\
+         2a3e7a7d7df7b10355165c8b9a6d0a7de8b062c4500dc4cd120c0f7418dae3d0\
+         b5781c34803fa75421c790dfe1de1834f280d7667b327f6c8cd7557e12ac3a0f\
+         93ec05c52e0493ef31a12d3d9260f79a289d6a379bc70c50841473d1a8cc81ec\
+         583e9645e07b8d9670655ba5bbcfecc6dc3966380ad8fecb17b6ba02469a020a\
+         84e18e8f84252070c13e9f1f289be54fbc481457778f616015e1327a02b140f1\
+         505eb309326d68378f8374595c849d84f4c333ec4423885143cb47bd71c5edae\
+         9be69a2ffeceb1bec9de244fbe15992b11b77c040f12bd8f6a975a44a0f90c29\
+         a9abc3d4d893927284c58754cce294529f8614dcd2aba991925fedc4ae74ffac\
+         6e333b93eb4aff0479da9a410e4450e0dd7ae4c6e2910900575da401fc07059f\
+         645e8b7e9bfdef33943054ff84011493c27b3429eaedb4ed5376441a77ed4385\
+         1ad77f16f541dfd269d50d6a5f14fb0aab1cbb4c1550be97f7ab4066193c4caa\
+         773dad38014bd2092fa755c824bb5e54c4f36ffda9fcea70b9c6e693e148c151",
+    );
+}
+
+#[test]
+fn xts_explicit_tweak() {
+    // Vector 15, with the tweak given as bytes instead of a sector number.
+    let xts = XTS::new(
+        Size::AES128,
+        as_vec("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0").as_slice(),
+        as_vec("bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0").as_slice(),
+    );
+    let mut tweak = [0u8; 16];
+    tweak[..5].copy_from_slice(&as_vec("9a78563412"));
+
+    let mut buf = as_vec("000102030405060708090a0b0c0d0e0f10");
+    aes_xts_encrypt(&xts, &tweak, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("6c1625db4671522d3d7599601de7ca09ed"));
+
+    aes_xts_decrypt(&xts, &tweak, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("000102030405060708090a0b0c0d0e0f10"));
+}
+
+#[test]
+fn xts_rejects_aes_192_and_equal_keys() {
+    assert_eq!(
+        XTS::try_new(Size::AES192, &[1u8; 24], &[2u8; 24]).err(),
+        Some(Error::BadKeyLength)
+    );
+    assert_eq!(
+        XTS::try_new(Size::AES128, &[7u8; 16], &[7u8; 16]).err(),
+        Some(Error::BadKeyLength)
+    );
+    assert_eq!(
+        XTS::try_new(Size::AES256, &[7u8; 32], &[7u8; 32]).err(),
+        Some(Error::BadKeyLength)
+    );
+    assert!(XTS::try_new(Size::AES128, &[7u8; 16], &[8u8; 16]).is_ok());
+}
+
+type StreamFn = fn(&mut AES, &mut [u8]);
+
+// Each vector is (size, key, plaintext, ciphertext), all with the same IV.
+fn check_stream(
+    mode: Mode,
+    iv: &str,
+    encrypt: StreamFn,
+    decrypt: StreamFn,
+    vectors: &[(Size, &str, &str, &str)],
+) {
+    for &(size, key, plain, cipher) in vectors.iter() {
+        let mut aes = AES::new(size, mode, as_vec(key).as_slice(), as_vec(iv).as_slice());
+        let mut buf = as_vec(plain);
+        encrypt(&mut aes, &mut buf);
+        assert_eq!(buf, as_vec(cipher));
+
+        aes.set_iv(as_vec(iv).as_slice()).unwrap();
+        decrypt(&mut aes, &mut buf);
+        assert_eq!(buf, as_vec(plain));
+    }
+}
+
+// F.3.1 - F.3.6   CFB1-AES128/192/256.Encrypt and .Decrypt
+#[test]
+fn cfb1_aes() {
+    let vectors = [
+        (
+            Size::AES128,
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "6bc1",
+            "68b3",
+        ),
+        (
+            Size::AES192,
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "6bc1",
+            "9359",
+        ),
+        (
+            Size::AES256,
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "6bc1",
+            "9029",
+        ),
+    ];
+
+    check_stream(
+        Mode::CFB1,
+        "000102030405060708090a0b0c0d0e0f",
+        aes_cfb1_encrypt_buffer,
+        aes_cfb1_decrypt_buffer,
+        &vectors,
+    );
+}
+
+// F.3.7 - F.3.12   CFB8-AES128/192/256.Encrypt and .Decrypt
+#[test]
+fn cfb8_aes() {
+    let vectors = [
+        (
+            Size::AES128,
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "6bc1bee22e409f96e93d7e117393172aae2d",
+            "3b79424c9c0dd436bace9e0ed4586a4f32b9",
+        ),
+        (
+            Size::AES192,
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "6bc1bee22e409f96e93d7e117393172aae2d",
+            "cda2521ef0a905ca44cd057cbf0d47a0678a",
+        ),
+        (
+            Size::AES256,
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "6bc1bee22e409f96e93d7e117393172aae2d",
+            "dc1f1a8520a64db55fcc8ac554844e889700",
+        ),
+    ];
+
+    check_stream(
+        Mode::CFB8,
+        "000102030405060708090a0b0c0d0e0f",
+        aes_cfb8_encrypt_buffer,
+        aes_cfb8_decrypt_buffer,
+        &vectors,
+    );
+}
+
+// F.3.13 - F.3.18   CFB128-AES128/192/256.Encrypt and .Decrypt
+#[test]
+fn cfb128_aes() {
+    let vectors = [
+        (
+            Size::AES128,
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+        ),
+        (
+            Size::AES192,
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "cdc80d6fddf18cab34c25909c99a417467ce7f7f81173621961a2b70171d3d7a\
+             2e1e8a1dd59b88b1c8e60fed1efac4c9c05f9f9ca9834fa042ae8fba584b09ff",
+        ),
+        (
+            Size::AES256,
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "dc7e84bfda79164b7ecd8486985d386039ffed143b28b1c832113c6331e5407b\
+             df10132415e54b92a13ed0a8267ae2f975a385741ab9cef82031623d55b1e471",
+        ),
+    ];
+
+    check_stream(
+        Mode::CFB128,
+        "000102030405060708090a0b0c0d0e0f",
+        aes_cfb128_encrypt_buffer,
+        aes_cfb128_decrypt_buffer,
+        &vectors,
+    );
+}
+
+#[test]
+fn cfb128_partial_segments() {
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("000102030405060708090a0b0c0d0e0f");
+    let plain = as_vec(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+    let cipher = as_vec(
+        "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+         26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+    );
+
+    let mut aes = AES::new(Size::AES128, Mode::CFB128, &key, &iv);
+    let mut buf = plain.clone();
+    for chunk in buf.chunks_mut(7) {
+        aes_cfb128_encrypt_buffer(&mut aes, chunk);
+    }
+    assert_eq!(buf, cipher);
+
+    aes.set_iv(&iv).unwrap();
+    let (head, tail) = buf.split_at_mut(21);
+    aes_cfb128_decrypt_buffer(&mut aes, head);
+    aes_cfb128_decrypt_buffer(&mut aes, tail);
+    assert_eq!(buf, plain);
+
+    // Assigning the IV in the middle of a block starts over from it.
+    aes_cfb128_encrypt_buffer(&mut aes, &mut [0u8; 5]);
+    aes.iv.copy_from_slice(&iv);
+    aes_cfb128_encrypt_buffer(&mut aes, &mut buf);
+    assert_eq!(buf, cipher);
+}
+
+// F.4.1 - F.4.6   OFB-AES128/192/256.Encrypt and .Decrypt
+#[test]
+fn ofb_aes() {
+    let vectors = [
+        (
+            Size::AES128,
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+             9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e",
+        ),
+        (
+            Size::AES192,
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "cdc80d6fddf18cab34c25909c99a4174fcc28b8d4c63837c09e81700c1100401\
+             8d9a9aeac0f6596f559c6d4daf59a5f26d9f200857ca6c3e9cac524bd9acc92a",
+        ),
+        (
+            Size::AES256,
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "dc7e84bfda79164b7ecd8486985d38604febdc6740d20b3ac88f6ad82a4fb08d\
+             71ab47a086e86eedf39d1c5bba97c4080126141d67f37be8538f5a8be740e484",
+        ),
+    ];
+
+    check_stream(
+        Mode::OFB,
+        "000102030405060708090a0b0c0d0e0f",
+        aes_ofb_xcrypt_buffer,
+        aes_ofb_xcrypt_buffer,
+        &vectors,
+    );
+}
+
+#[test]
+fn ofb_resumes_mid_block() {
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("000102030405060708090a0b0c0d0e0f");
+    let plain = as_vec(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+    let cipher = as_vec(
+        "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+         9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e",
+    );
+
+    let mut aes = AES::new(Size::AES128, Mode::OFB, &key, &iv);
+    let mut buf = plain.clone();
+    for chunk in buf.chunks_mut(5) {
+        aes_ofb_xcrypt_buffer(&mut aes, chunk);
+    }
+    assert_eq!(buf, cipher);
+
+    aes.set_iv(&iv).unwrap();
+    let (head, tail) = buf.split_at_mut(33);
+    aes_ofb_xcrypt_buffer(&mut aes, head);
+    aes_ofb_xcrypt_buffer(&mut aes, tail);
+    assert_eq!(buf, plain);
+
+    // Assigning the IV in the middle of a block starts over from it.
+    aes_ofb_xcrypt_buffer(&mut aes, &mut [0u8; 5]);
+    aes.iv.copy_from_slice(&iv);
+    aes_ofb_xcrypt_buffer(&mut aes, &mut buf);
+    assert_eq!(buf, cipher);
+}
+
+// Each vector is [M, T].
+fn check_cmac(size: Size, key: &str, vectors: &[[&str; 2]]) {
+    let aes = AES::new(size, Mode::CMAC, as_vec(key).as_slice(), &[0u8; 16]);
+    for v in vectors.iter() {
+        let msg = as_vec(v[0]);
+        let tag = as_vec(v[1]);
+        assert_eq!(&aes_cmac(&aes, &msg)[..], tag.as_slice());
+        aes_cmac_verify(&aes, &msg, &tag).unwrap();
+
+        // Feeding the message in pieces gives the same tag.
+        for step in 1..18 {
+            let mut mac = CMAC::new(&aes);
+            for chunk in msg.chunks(step) {
+                mac.update(chunk);
+            }
+            assert_eq!(&mac.finalize()[..], tag.as_slice());
+        }
+    }
+}
+
+// RFC 4493 Section 4, AES-128 Examples 1 to 4
+#[test]
+fn cmac_aes_128() {
+    let vectors = [
+        [
+            "",
+            "bb1d6929e95937287fa37d129b756746",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172a",
+            "070a16b46b4d4144f79bdd9dd04a287c",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411",
+            "dfa66747de9ae63030ca32611497c827",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "51f0bebf7e3b9d92fc49741779363cfe",
+        ],
+    ];
+
+    check_cmac(Size::AES128, "2b7e151628aed2a6abf7158809cf4f3c", &vectors);
+}
+
+// SP 800-38B Appendix D.2, CMAC-AES192 Examples 5 to 8
+#[test]
+fn cmac_aes_192() {
+    let vectors = [
+        [
+            "",
+            "d17ddf46adaacde531cac483de7a9367",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172a",
+            "9e99a7bf31e710900662f65e617c5184",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411",
+            "8a1de5be2eb31aad089a82e6ee908b0e",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "a1d5df0eed790f794d77589659f39a11",
+        ],
+    ];
+
+    check_cmac(Size::AES192, "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b", &vectors);
+}
+
+// SP 800-38B Appendix D.3, CMAC-AES256 Examples 9 to 12
+#[test]
+fn cmac_aes_256() {
+    let vectors = [
+        [
+            "",
+            "028962f61b7bf89efc6b551f4667d983",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172a",
+            "28a7023f452e8f82bd4bf28d8c37c35c",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411",
+            "aaf3d8f1de5640c232f5b169b9c911e6",
+        ],
+        [
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+            "e1992190549f6ed5696a2c056c315410",
+        ],
+    ];
+
+    check_cmac(Size::AES256, "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4", &vectors);
+}
+
+#[test]
+fn cmac_verify() {
+    let aes = AES::new(
+        Size::AES128,
+        Mode::CMAC,
+        as_vec("2b7e151628aed2a6abf7158809cf4f3c").as_slice(),
+        &[0u8; 16],
+    );
+    let msg = as_vec("6bc1bee22e409f96e93d7e117393172a");
+
+    // Truncated tags are accepted.
+    aes_cmac_verify(&aes, &msg, &as_vec("070a16b46b4d4144")).unwrap();
+
+    let mut tag = as_vec("070a16b46b4d4144f79bdd9dd04a287c");
+    tag[15] ^= 1;
+    assert_eq!(aes_cmac_verify(&aes, &msg, &tag), Err(Error::Authentication));
+    assert_eq!(aes_cmac_verify(&aes, &msg, &[]), Err(Error::BadTagLength));
+}
+
+type KeyWrapFn = fn(&AES, &[u8]) -> Result<Vec<u8>, Error>;
+
+// Each vector is (size, KEK, key data, wrapped key).
+fn check_key_wrap(
+    mode: Mode,
+    wrap: KeyWrapFn,
+    unwrap: KeyWrapFn,
+    vectors: &[(Size, &str, &str, &str)],
+) {
+    for &(size, kek, key, wrapped) in vectors.iter() {
+        let aes = AES::new(size, mode, as_vec(kek).as_slice(), &[0u8; 16]);
+        assert_eq!(wrap(&aes, &as_vec(key)).unwrap(), as_vec(wrapped));
+        assert_eq!(unwrap(&aes, &as_vec(wrapped)).unwrap(), as_vec(key));
+
+        let mut corrupted = as_vec(wrapped);
+        corrupted[0] ^= 1;
+        assert_eq!(unwrap(&aes, &corrupted), Err(Error::Authentication));
+        corrupted[0] ^= 1;
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0x80;
+        assert_eq!(unwrap(&aes, &corrupted), Err(Error::Authentication));
+    }
+}
+
+// RFC 3394 Section 4.1 to 4.6
+#[test]
+fn kw_rfc3394() {
+    let vectors = [
+        (
+            // 4.1
+            Size::AES128,
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5",
+        ),
+        (
+            // 4.2
+            Size::AES192,
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "00112233445566778899aabbccddeeff",
+            "96778b25ae6ca435f92b5b97c050aed2468ab8a17ad84e5d",
+        ),
+        (
+            // 4.3
+            Size::AES256,
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff",
+            "64e8c3f9ce0f5ba263e9777905818a2a93c8191e7d6e8ae7",
+        ),
+        (
+            // 4.4
+            Size::AES192,
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "00112233445566778899aabbccddeeff0001020304050607",
+            "031d33264e15d33268f24ec260743edce1c6c7ddee725a936ba814915c6762d2",
+        ),
+        (
+            // 4.5
+            Size::AES256,
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff0001020304050607",
+            "a8f9bc1612c68b3ff6e6f4fbe30e71e4769c8b80a32cb8958cd5d17d6b254da1",
+        ),
+        (
+            // 4.6
+            Size::AES256,
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f",
+            "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43b\
+             fb988b9b7a02dd21",
+        ),
+    ];
+
+    check_key_wrap(Mode::KW, aes_kw_wrap, aes_kw_unwrap, &vectors);
+}
+
+// RFC 5649 Section 6
+#[test]
+fn kwp_rfc5649() {
+    let vectors = [
+        (
+            Size::AES192,
+            "5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8",
+            "c37b7e6492584340bed12207808941155068f738",
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+        ),
+        (
+            Size::AES192,
+            "5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8",
+            "466f7250617369",
+            "afbeb0f07dfbf5419200f2ccb50bb24f",
+        ),
+    ];
+
+    check_key_wrap(Mode::KWP, aes_kwp_wrap, aes_kwp_unwrap, &vectors);
+}
+
+#[test]
+fn kw_rejects_malformed_input() {
+    let aes = AES::new(Size::AES128, Mode::KW, &[0u8; 16], &[0u8; 16]);
+    assert_eq!(aes_kw_unwrap(&aes, &[0u8; 16]), Err(Error::Authentication));
+    assert_eq!(aes_kw_unwrap(&aes, &[0u8; 25]), Err(Error::Authentication));
+    assert_eq!(aes_kwp_unwrap(&aes, &[0u8; 8]), Err(Error::Authentication));
+
+    // A KW wrapped key does not unwrap as KWP and vice versa.
+    let wrapped = aes_kw_wrap(&aes, &[0x42u8; 16]).unwrap();
+    assert_eq!(aes_kwp_unwrap(&aes, &wrapped), Err(Error::Authentication));
+    let wrapped = aes_kwp_wrap(&aes, &[0x42u8; 16]).unwrap();
+    assert_eq!(aes_kw_unwrap(&aes, &wrapped), Err(Error::Authentication));
+}
+
+// Each vector is (key, associated data items, P, V || C).
+fn check_siv(key: &str, ad: &[&str], plain: &str, output: &str) {
+    let siv = SIV::new(as_vec(key).as_slice());
+    let ad: Vec<Vec<u8>> = ad.iter().map(|a| as_vec(a)).collect();
+    let ad: Vec<&[u8]> = ad.iter().map(|a| a.as_slice()).collect();
+    let output = as_vec(output);
+
+    let mut buf = as_vec(plain);
+    let mut v = [0u8; 16];
+    aes_siv_seal(&siv, &ad, &mut buf, &mut v).unwrap();
+    assert_eq!(&v[..], &output[..16]);
+    assert_eq!(buf.as_slice(), &output[16..]);
+
+    aes_siv_open(&siv, &ad, &mut buf, &v).unwrap();
+    assert_eq!(buf, as_vec(plain));
+}
+
+// RFC 5297 Appendix A.1, Deterministic Authenticated Encryption Example
+#[test]
+fn siv_deterministic() {
+    check_siv(
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff",
+        &["101112131415161718191a1b1c1d1e1f2021222324252627"],
+        "112233445566778899aabbccddee",
+        "85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c",
+    );
+}
+
+// RFC 5297 Appendix A.2, Nonce-Based Authenticated Encryption Example
+#[test]
+fn siv_nonce_based() {
+    check_siv(
+        "7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f",
+        &[
+            "00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100",
+            "102030405060708090a0",
+            "09f911029d74e35bd84156c5635688c0",
+        ],
+        "7468697320697320736f6d6520706c61696e7465787420746f20656e6372797074207573696e67205349562d414553",
+        "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+         dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d",
+    );
+}
+
+// A.1 with 384 and 512 bit keys, cross-checked against OpenSSL.
+#[test]
+fn siv_larger_keys() {
+    check_siv(
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8\
+         f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff0001020304050607",
+        &["101112131415161718191a1b1c1d1e1f2021222324252627"],
+        "112233445566778899aabbccddee",
+        "83861fb28f702566746da2e41693c60865b72fab3ba49386be7e675411b2",
+    );
+    check_siv(
+        "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0efeeedecebeae9e8e7e6e5e4e3e2e1e0\
+         f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff000102030405060708090a0b0c0d0e0f",
+        &["101112131415161718191a1b1c1d1e1f2021222324252627"],
+        "112233445566778899aabbccddee",
+        "59e71a7723eaa441e91cb355cdeaba2b728fec0c1a9a76dc972e9751f4f7",
+    );
+}
+
+#[test]
+fn siv_rejects_modified_message() {
+    let siv = SIV::new(&[3u8; 32]);
+    let mut buf = b"deduplicated block".to_vec();
+    let mut v = [0u8; 16];
+    aes_siv_seal(&siv, &[b"a", b"b"], &mut buf, &mut v).unwrap();
+
+    // The same input always gives the same output.
+    let mut again = b"deduplicated block".to_vec();
+    let mut v2 = [0u8; 16];
+    aes_siv_seal(&siv, &[b"a", b"b"], &mut again, &mut v2).unwrap();
+    assert_eq!((v, &buf), (v2, &again));
+
+    let sealed = buf.clone();
+    assert_eq!(
+        aes_siv_open(&siv, &[b"b", b"a"], &mut buf, &v),
+        Err(Error::Authentication)
+    );
+    assert_eq!(buf, sealed);
+}
+
+// Each vector is [K, N, AAD, P, C || T].
+fn check_gcm_siv(size: Size, vectors: &[[&str; 5]]) {
+    for v in vectors.iter() {
+        let aes = AES::new(size, Mode::GCMSIV, as_vec(v[0]).as_slice(), &[0u8; 16]);
+        let mut nonce = [0u8; 12];
+        nonce.copy_from_slice(&as_vec(v[1]));
+        let aad = as_vec(v[2]);
+        let expected = as_vec(v[4]);
+        let (ciphertext, expected_tag) = expected.split_at(expected.len() - 16);
+
+        let mut buf = as_vec(v[3]);
+        let mut tag = [0u8; 16];
+        aes_gcm_siv_seal(&aes, &nonce, &aad, &mut buf, &mut tag).unwrap();
+        assert_eq!(buf.as_slice(), ciphertext);
+        assert_eq!(&tag[..], expected_tag);
+
+        aes_gcm_siv_open(&aes, &nonce, &aad, &mut buf, &tag).unwrap();
+        assert_eq!(buf, as_vec(v[3]));
+
+        tag[0] ^= 1;
+        assert_eq!(
+            aes_gcm_siv_open(&aes, &nonce, &aad, &mut buf, &tag),
+            Err(Error::Authentication)
+        );
+        assert_eq!(buf, as_vec(v[3]));
+    }
+}
+
+// RFC 8452 Appendix C.1, AEAD_AES_128_GCM_SIV
+#[test]
+fn gcm_siv_aes_128() {
+    let vectors = [
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "",
+            "dc20e2d83f25705bb49e439eca56de25",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000",
+            "b5d839330ac7b786578782fff6013b815b287c22493a364c",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "010000000000000000000000",
+            "7323ea61d05932260047d942a4978db357391a0bc4fdec8b0d106639",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "01000000000000000000000000000000",
+            "743f7c8077ab25f8624e2e948579cf77303aaf90f6fe21199c6068577437a0c4",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000000000000000000002000000000000000000000000000000",
+            "84e07e62ba83a6585417245d7ec413a9fe427d6315c09b57ce45f2e3936a9445\
+             1a8e45dcd4578c667cd86847bf6155ff",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000000000000000000002000000000000000000000000000000\
+             03000000000000000000000000000000",
+            "3fd24ce1f5a67b75bf2351f181a475c7b800a5b4d3dcf70106b1eea82fa1d64d\
+             f42bf7226122fa92e17a40eeaac1201b5e6e311dbf395d35b0fe39c2714388f8",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000000000000000000002000000000000000000000000000000\
+             0300000000000000000000000000000004000000000000000000000000000000",
+            "2433668f1058190f6d43e360f4f35cd8e475127cfca7028ea8ab5c20f7ab2af0\
+             2516a2bdcbc08d521be37ff28c152bba36697f25b4cd169c6590d1dd39566d3f\
+             8a263dd317aa88d56bdf3936dba75bb8",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000",
+            "1e6daba35669f4273b0a1a2560969cdf790d99759abd1508",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "020000000000000000000000",
+            "296c7889fd99f41917f4462008299c5102745aaa3a0c469fad9e075a",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "02000000000000000000000000000000",
+            "e2b0c5da79a901c1745f700525cb335b8f8936ec039e4e4bb97ebd8c4457441f",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000000000000000000003000000000000000000000000000000",
+            "620048ef3c1e73e57e02bb8562c416a319e73e4caac8e96a1ecb2933145a1d71\
+             e6af6a7f87287da059a71684ed3498e1",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000000000000000000003000000000000000000000000000000\
+             04000000000000000000000000000000",
+            "50c8303ea93925d64090d07bd109dfd9515a5a33431019c17d93465999a8b005\
+             3201d723120a8562b838cdff25bf9d1e6a8cc3865f76897c2e4b245cf31c51f2",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000000000000000000003000000000000000000000000000000\
+             0400000000000000000000000000000005000000000000000000000000000000",
+            "2f5c64059db55ee0fb847ed513003746aca4e61c711b5de2e7a77ffd02da42fe\
+             ec601910d3467bb8b36ebbaebce5fba30d36c95f48a3e7980f0e7ac299332a80\
+             cdc46ae475563de037001ef84ae21744",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "010000000000000000000000",
+            "02000000",
+            "a8fe3e8707eb1f84fb28f8cb73de8e99e2f48a14",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "010000000000000000000000000000000200",
+            "0300000000000000000000000000000004000000",
+            "6bb0fecf5ded9b77f902c7d5da236a4391dd029724afc9805e976f451e6d87f6\
+             fe106514",
+        ],
+        [
+            "01000000000000000000000000000000",
+            "030000000000000000000000",
+            "0100000000000000000000000000000002000000",
+            "030000000000000000000000000000000400",
+            "44d0aaf6fb2f1f34add5e8064e83e12a2adabff9b2ef00fb47920cc72a0c0f13\
+             b9fd",
+        ],
+    ];
+
+    check_gcm_siv(Size::AES128, &vectors);
+}
+
+// RFC 8452 Appendix C.2, AEAD_AES_256_GCM_SIV
+#[test]
+fn gcm_siv_aes_256() {
+    let vectors = [
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "",
+            "07f5f4169bbf55a8400cd47ea6fd400f",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000",
+            "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "010000000000000000000000",
+            "9aab2aeb3faa0a34aea8e2b18ca50da9ae6559e48fd10f6e5c9ca17e",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "01000000000000000000000000000000",
+            "85a01b63025ba19b7fd3ddfc033b3e76c9eac6fa700942702e90862383c6c366",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000000000000000000002000000000000000000000000000000",
+            "4a6a9db4c8c6549201b9edb53006cba821ec9cf850948a7c86c68ac7539d027f\
+             e819e63abcd020b006a976397632eb5d",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000000000000000000002000000000000000000000000000000\
+             03000000000000000000000000000000",
+            "c00d121893a9fa603f48ccc1ca3c57ce7499245ea0046db16c53c7c66fe717e3\
+             9cf6c748837b61f6ee3adcee17534ed5790bc96880a99ba804bd12c0e6a22cc4",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "",
+            "0100000000000000000000000000000002000000000000000000000000000000\
+             0300000000000000000000000000000004000000000000000000000000000000",
+            "c2d5160a1f8683834910acdafc41fbb1632d4a353e8b905ec9a5499ac34f96c7\
+             e1049eb080883891a4db8caaa1f99dd004d80487540735234e3744512c6f90ce\
+             112864c269fc0d9d88c61fa47e39aa08",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000",
+            "1de22967237a813291213f267e3b452f02d01ae33e4ec854",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "020000000000000000000000",
+            "163d6f9cc1b346cd453a2e4cc1a4a19ae800941ccdc57cc8413c277f",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "02000000000000000000000000000000",
+            "c91545823cc24f17dbb0e9e807d5ec17b292d28ff61189e8e49f3875ef91aff7",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000000000000000000003000000000000000000000000000000",
+            "07dad364bfc2b9da89116d7bef6daaaf6f255510aa654f920ac81b94e8bad365\
+             aea1bad12702e1965604374aab96dbbc",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000000000000000000003000000000000000000000000000000\
+             04000000000000000000000000000000",
+            "c67a1f0f567a5198aa1fcc8e3f21314336f7f51ca8b1af61feac35a86416fa47\
+             fbca3b5f749cdf564527f2314f42fe2503332742b228c647173616cfd44c54eb",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "01",
+            "0200000000000000000000000000000003000000000000000000000000000000\
+             0400000000000000000000000000000005000000000000000000000000000000",
+            "67fd45e126bfb9a79930c43aad2d36967d3f0e4d217c1e551f59727870beefc9\
+             8cb933a8fce9de887b1e40799988db1fc3f91880ed405b2dd298318858467c89\
+             5bde0285037c5de81e5b570a049b62a0",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "010000000000000000000000",
+            "02000000",
+            "22b3f4cd1835e517741dfddccfa07fa4661b74cf",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "010000000000000000000000000000000200",
+            "0300000000000000000000000000000004000000",
+            "43dd0163cdb48f9fe3212bf61b201976067f342bb879ad976d8242acc188ab59\
+             cabfe307",
+        ],
+        [
+            "0100000000000000000000000000000000000000000000000000000000000000",
+            "030000000000000000000000",
+            "0100000000000000000000000000000002000000",
+            "030000000000000000000000000000000400",
+            "462401724b5ce6588d5a54aae5375513a075cfcdf5042112aa29685c912fc205\
+             6543",
+        ],
+    ];
+
+    check_gcm_siv(Size::AES256, &vectors);
+}
+
+// RFC 8452 Appendix C.3, Counter Wrap Tests
+#[test]
+fn gcm_siv_counter_wrap() {
+    let vectors = [
+        [
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "000000000000000000000000000000004db923dc793ee6497c76dcc03a98e108",
+            "f3f80f2cf0cb2dd9c5984fcda908456cc537703b5ba70324a6793a7bf218d3ea\
+             ffffffff000000000000000000000000",
+        ],
+        [
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "000000000000000000000000",
+            "",
+            "eb3640277c7ffd1303c7a542d02d3e4c0000000000000000",
+            "18ce4f0b8cb4d0cac65fea8f79257b20888e53e72299e56dffffffff00000000\
+             0000000000000000",
+        ],
+    ];
+
+    check_gcm_siv(Size::AES256, &vectors);
+}
+
+// Each vector is [MSG, KEY, NONCE, HEADER, CIPHER || TAG].
+fn check_eax(vectors: &[[&str; 5]]) {
+    for v in vectors.iter() {
+        let aes = AES::new(Size::AES128, Mode::EAX, as_vec(v[1]).as_slice(), &[0u8; 16]);
+        let nonce = as_vec(v[2]);
+        let header = as_vec(v[3]);
+        let expected = as_vec(v[4]);
+        let (ciphertext, expected_tag) = expected.split_at(expected.len() - 16);
+
+        let mut buf = as_vec(v[0]);
+        let mut tag = [0u8; 16];
+        aes_eax_seal(&aes, &nonce, &header, &mut buf, &mut tag).unwrap();
+        assert_eq!(buf.as_slice(), ciphertext);
+        assert_eq!(&tag[..], expected_tag);
+
+        aes_eax_open(&aes, &nonce, &header, &mut buf, &tag).unwrap();
+        assert_eq!(buf, as_vec(v[0]));
+    }
+}
+
+// Test vectors from "The EAX Mode of Operation", Appendix E
+#[test]
+fn eax_aes_128() {
+    let vectors = [
+        [
+            "",
+            "233952dee4d5ed5f9b9c6d6ff80ff478",
+            "62ec67f9c3a4a407fcb2a8c49031a8b3",
+            "6bfb914fd07eae6b",
+            "e037830e8389f27b025a2d6527e79d01",
+        ],
+        [
+            "f7fb",
+            "91945d3f4dcbee0bf45ef52255f095a4",
+            "becaf043b0a23d843194ba972c66debd",
+            "fa3bfd4806eb53fa",
+            "19dd5c4c9331049d0bdab0277408f67967e5",
+        ],
+        [
+            "1a47cb4933",
+            "01f74ad64077f2e704c0f60ada3dd523",
+            "70c3db4f0d26368400a10ed05d2bff5e",
+            "234a3463c1264ac6",
+            "d851d5bae03a59f238a23e39199dc9266626c40f80",
+        ],
+        [
+            "481c9e39b1",
+            "d07cf6cbb7f313bdde66b727afd3c5e8",
+            "8408dfff3c1a2b1292dc199e46b7d617",
+            "33cce2eabff5a79d",
+            "632a9d131ad4c168a4225d8e1ff755939974a7bede",
+        ],
+        [
+            "40d0c07da5e4",
+            "35b6d0580005bbc12b0587124557d2c2",
+            "fdb6b06676eedc5c61d74276e1f8e816",
+            "aeb96eaebe2970e9",
+            "071dfe16c675cb0677e536f73afe6a14b74ee49844dd",
+        ],
+        [
+            "4de3b35c3fc039245bd1fb7d",
+            "bd8e6e11475e60b268784c38c62feb22",
+            "6eac5c93072d8e8513f750935e46da1b",
+            "d4482d1ca78dce0f",
+            "835bb4f15d743e350e728414abb8644fd6ccb86947c5e10590210a4f",
+        ],
+        [
+            "8b0a79306c9ce7ed99dae4f87f8dd61636",
+            "7c77d6e813bed5ac98baa417477a2e7d",
+            "1a8c98dcd73d38393b2bf1569deefc19",
+            "65d2017990d62528",
+            "02083e3979da014812f59f11d52630da30137327d10649b0aa6e1c181db617d7\
+             f2",
+        ],
+        [
+            "1bda122bce8a8dbaf1877d962b8592dd2d56",
+            "5fff20cafab119ca2fc73549e20f5b0d",
+            "dde59b97d722156d4d9aff2bc7559826",
+            "54b9f04e6a09189a",
+            "2ec47b2c4954a489afc7ba4897edcdae8cc33b60450599bd02c96382902aef7f\
+             832a",
+        ],
+        [
+            "6cf36720872b8513f6eab1a8a44438d5ef11",
+            "a4a4782bcffd3ec5e7ef6d8c34a56123",
+            "b781fcf2f75fa5a8de97a9ca48e522ec",
+            "899a175897561d7e",
+            "0de18fd0fdd91e7af19f1d8ee8733938b1e8e7f6d2231618102fdb7fe55ff199\
+             1700",
+        ],
+        [
+            "ca40d7446e545ffaed3bd12a740a659ffbbb3ceab7",
+            "8395fcf1e95bebd697bd010bc766aac3",
+            "22e7add93cfc6393c57ec0b3c17d6b44",
+            "126735fcc320d25a",
+            "cb8920f87a6c75cff39627b56e3ed197c552d295a7cfc46afc253b4652b1af37\
+             95b124ab6e",
+        ],
+    ];
+
+    check_eax(&vectors);
+}
+
+#[test]
+fn eax_truncated_tag() {
+    // Vector 2 with a 64 bit tag.
+    let aes = AES::new(
+        Size::AES128,
+        Mode::EAX,
+        as_vec("91945d3f4dcbee0bf45ef52255f095a4").as_slice(),
+        &[0u8; 16],
+    );
+    let nonce = as_vec("becaf043b0a23d843194ba972c66debd");
+    let header = as_vec("fa3bfd4806eb53fa");
+
+    let mut buf = as_vec("f7fb");
+    let mut tag = [0u8; 8];
+    aes_eax_seal(&aes, &nonce, &header, &mut buf, &mut tag).unwrap();
+    assert_eq!(buf, as_vec("19dd"));
+    assert_eq!(&tag[..], as_vec("5c4c9331049d0bda").as_slice());
+
+    tag[7] ^= 1;
+    assert_eq!(
+        aes_eax_open(&aes, &nonce, &header, &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+    assert_eq!(buf, as_vec("19dd"));
+}
+
+// Each vector is [NONCE, A, P, C || TAG], under the key 000102...0f.
+fn check_ocb(vectors: &[[&str; 4]]) {
+    let aes = AES::new(
+        Size::AES128,
+        Mode::OCB,
+        as_vec("000102030405060708090a0b0c0d0e0f").as_slice(),
+        &[0u8; 16],
+    );
+    for v in vectors.iter() {
+        let nonce = as_vec(v[0]);
+        let aad = as_vec(v[1]);
+        let expected = as_vec(v[3]);
+        let (ciphertext, expected_tag) = expected.split_at(expected.len() - 16);
+
+        let mut buf = as_vec(v[2]);
+        let mut tag = [0u8; 16];
+        aes_ocb_seal(&aes, &nonce, &aad, &mut buf, &mut tag).unwrap();
+        assert_eq!(buf.as_slice(), ciphertext);
+        assert_eq!(&tag[..], expected_tag);
+
+        aes_ocb_open(&aes, &nonce, &aad, &mut buf, &tag).unwrap();
+        assert_eq!(buf, as_vec(v[2]));
+    }
+}
+
+// RFC 7253 Appendix A, sample results
+#[test]
+fn ocb_aes_128() {
+    let vectors = [
+        [
+            "bbaa99887766554433221100",
+            "",
+            "",
+            "785407bfffc8ad9edcc5520ac9111ee6",
+        ],
+        [
+            "bbaa99887766554433221101",
+            "0001020304050607",
+            "0001020304050607",
+            "6820b3657b6f615a5725bda0d3b4eb3a257c9af1f8f03009",
+        ],
+        [
+            "bbaa99887766554433221102",
+            "0001020304050607",
+            "",
+            "81017f8203f081277152fade694a0a00",
+        ],
+        [
+            "bbaa99887766554433221103",
+            "",
+            "0001020304050607",
+            "45dd69f8f5aae72414054cd1f35d82760b2cd00d2f99bfa9",
+        ],
+        [
+            "bbaa99887766554433221104",
+            "000102030405060708090a0b0c0d0e0f",
+            "000102030405060708090a0b0c0d0e0f",
+            "571d535b60b277188be5147170a9a22c3ad7a4ff3835b8c5701c1ccec8fc3358",
+        ],
+        [
+            "bbaa99887766554433221105",
+            "000102030405060708090a0b0c0d0e0f",
+            "",
+            "8cf761b6902ef764462ad86498ca6b97",
+        ],
+        [
+            "bbaa99887766554433221106",
+            "",
+            "000102030405060708090a0b0c0d0e0f",
+            "5ce88ec2e0692706a915c00aeb8b2396f40e1c743f52436bdf06d8fa1eca343d",
+        ],
+        [
+            "bbaa99887766554433221107",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "1ca2207308c87c010756104d8840ce1952f09673a448a122c92c62241051f573\
+             56d7f3c90bb0e07f",
+        ],
+        [
+            "bbaa99887766554433221108",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "",
+            "6dc225a071fc1b9f7c69f93b0f1e10de",
+        ],
+        [
+            "bbaa99887766554433221109",
+            "",
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "221bd0de7fa6fe993eccd769460a0af2d6cded0c395b1c3ce725f32494b9f914\
+             d85c0b1eb38357ff",
+        ],
+        [
+            "bbaa9988776655443322110a",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "bd6f6c496201c69296c11efd138a467abd3c707924b964deaffc40319af5a485\
+             40fbba186c5553c68ad9f592a79a4240",
+        ],
+        [
+            "bbaa9988776655443322110b",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "",
+            "fe80690bee8a485d11f32965bc9d2a32",
+        ],
+        [
+            "bbaa9988776655443322110c",
+            "",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "2942bfc773bda23cabc6acfd9bfd5835bd300f0973792ef46040c53f1432bcdf\
+             b5e1dde3bc18a5f840b52e653444d5df",
+        ],
+        [
+            "bbaa9988776655443322110d",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+             2021222324252627",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+             2021222324252627",
+            "d5ca91748410c1751ff8a2f618255b68a0a12e093ff454606e59f9c1d0ddc54b\
+             65e8628e568bad7aed07ba06a4a69483a7035490c5769e60",
+        ],
+        [
+            "bbaa9988776655443322110e",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+             2021222324252627",
+            "",
+            "c5cd9d1850c141e358649994ee701b68",
+        ],
+        [
+            "bbaa9988776655443322110f",
+            "",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+             2021222324252627",
+            "4412923493c57d5de0d700f753cce0d1d2d95060122e9f15a5ddbfc5787e50b5\
+             cc55ee507bcb084e479ad363ac366b95a98ca5f3000b1479",
+        ],
+    ];
+
+    check_ocb(&vectors);
+}
+
+// RFC 7253 Appendix A, the sample result with a 96 bit tag
+#[test]
+fn ocb_tag_96() {
+    let aes = AES::new(
+        Size::AES128,
+        Mode::OCB,
+        as_vec("0f0e0d0c0b0a09080706050403020100").as_slice(),
+        &[0u8; 16],
+    );
+    let nonce = as_vec("bbaa9988776655443322110d");
+    let data: Vec<u8> = (0..40).collect();
+
+    let mut buf = data.clone();
+    let mut tag = [0u8; 12];
+    aes_ocb_seal(&aes, &nonce, &data, &mut buf, &mut tag).unwrap();
+    assert_eq!(
+        buf,
+        as_vec("1792a4e31e0755fb03e31b22116e6c2ddf9efd6e33d536f1a0124b0a55bae884\
+                ed93481529c76b6a")
+    );
+    assert_eq!(&tag[..], as_vec("d0c515f4d1cdd4fdac4f02aa").as_slice());
+
+    aes_ocb_open(&aes, &nonce, &data, &mut buf, &tag).unwrap();
+    assert_eq!(buf, data);
+}
+
+// RFC 7253 Appendix A, the iterative test over a wider variety of inputs
+#[test]
+fn ocb_iterative() {
+    let results = [
+        (Size::AES128, 16, "67e944d23256c5e0b6c61fa22fdf1ea2"),
+        (Size::AES192, 16, "f673f2c3e7174aae7bae986ca9f29e17"),
+        (Size::AES256, 16, "d90eb8e9c977c88b79dd793d7ffa161c"),
+        (Size::AES128, 12, "77a3d8e73589158d25d01209"),
+        (Size::AES192, 12, "05d56ead2752c86be6932c5e"),
+        (Size::AES256, 12, "5458359ac23b0cba9e6330dd"),
+        (Size::AES128, 8, "192c9b7bd90ba06a"),
+        (Size::AES192, 8, "0066bc6e0ef34e24"),
+        (Size::AES256, 8, "7d4ea5d445501cbe"),
+    ];
+
+    for &(size, tag_len, expected) in results.iter() {
+        let key_len = match size {
+            Size::AES128 => 16,
+            Size::AES192 => 24,
+            Size::AES256 => 32,
+        };
+        let mut key = vec![0u8; key_len];
+        key[key_len - 1] = (tag_len * 8) as u8;
+        let aes = AES::new(size, Mode::OCB, &key, &[0u8; 16]);
+
+        let mut c = Vec::new();
+        let seal = |n: u64, aad: &[u8], plain: &[u8]| {
+            let mut nonce = [0u8; 12];
+            nonce[4..].copy_from_slice(&n.to_be_bytes());
+            let mut buf = plain.to_vec();
+            let mut tag = vec![0u8; tag_len];
+            aes_ocb_seal(&aes, &nonce, aad, &mut buf, &mut tag).unwrap();
+            buf.extend_from_slice(&tag);
+            buf
+        };
+        for i in 0..128 {
+            let s = vec![0u8; i as usize];
+            c.extend(seal(3 * i + 1, &s, &s));
+            c.extend(seal(3 * i + 2, &[], &s));
+            c.extend(seal(3 * i + 3, &s, &[]));
+        }
+        assert_eq!(seal(385, &c, &[]), as_vec(expected));
+    }
+}
+
+#[test]
+fn ocb_rejects_modified_message() {
+    let aes = AES::new(Size::AES128, Mode::OCB, &[0u8; 16], &[0u8; 16]);
+    let nonce = [0u8; 12];
+    // A full and a partial block, which are decrypted differently.
+    let plain = as_vec("000000000000000000000000000000000000000000");
+
+    let mut buf = plain.clone();
+    let mut tag = [0u8; 16];
+    aes_ocb_seal(&aes, &nonce, b"header", &mut buf, &mut tag).unwrap();
+    let sealed = buf.clone();
+
+    for &i in [3, 18].iter() {
+        buf[i] ^= 1;
+        assert_eq!(
+            aes_ocb_open(&aes, &nonce, b"header", &mut buf, &tag),
+            Err(Error::Authentication)
+        );
+        buf[i] ^= 1;
+    }
+    assert_eq!(
+        aes_ocb_open(&aes, &nonce, b"Header", &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+    // The ciphertext is restored when verification fails.
+    assert_eq!(buf, sealed);
+
+    tag[15] ^= 0x80;
+    assert_eq!(
+        aes_ocb_open(&aes, &nonce, b"header", &mut buf, &tag),
+        Err(Error::Authentication)
+    );
+    assert_eq!(buf, sealed);
+}
+
+type CtsFn = fn(&mut AES, &mut [u8]) -> Result<(), Error>;
+
+// Each vector is [PLAIN, CBC-CS1, CBC-CS2, CBC-CS3], with a zero IV.
+fn check_cbc_cs(key: &str, vectors: &[[&str; 4]]) {
+    let mut aes = AES::new(Size::AES128, Mode::CBCCS3, as_vec(key).as_slice(), &[0u8; 16]);
+    let variants: [(CtsFn, CtsFn); 3] = [
+        (aes_cbc_cs1_encrypt_buffer, aes_cbc_cs1_decrypt_buffer),
+        (aes_cbc_cs2_encrypt_buffer, aes_cbc_cs2_decrypt_buffer),
+        (aes_cbc_cs3_encrypt_buffer, aes_cbc_cs3_decrypt_buffer),
+    ];
+    for v in vectors.iter() {
+        for (&(encrypt, decrypt), expected) in variants.iter().zip(v[1..].iter()) {
+            let mut buf = as_vec(v[0]);
+            aes.set_iv(&[0u8; 16]).unwrap();
+            encrypt(&mut aes, &mut buf).unwrap();
+            assert_eq!(buf, as_vec(expected));
+
+            aes.set_iv(&[0u8; 16]).unwrap();
+            decrypt(&mut aes, &mut buf).unwrap();
+            assert_eq!(buf, as_vec(v[0]));
+        }
+    }
+}
+
+// RFC 3962 Appendix B, the AES-128 CBC-CS3 examples of Kerberos. The CS1
+// and CS2 ciphertexts are the same blocks in the order of those variants.
+#[test]
+fn cbc_cs_aes_128() {
+    let vectors = [
+        [
+            "4920776f756c64206c696b652074686520",
+            "97c6353568f2bf8cb4d8a580362da7ff7f",
+            "c6353568f2bf8cb4d8a580362da7ff7f97",
+            "c6353568f2bf8cb4d8a580362da7ff7f97",
+        ],
+        [
+            "4920776f756c64206c696b65207468652047656e6572616c20476175277320",
+            "97687268d6ecccc0c07b25e25ecfe5fc00783e0efdb2c1d445d4c8eff7ed22",
+            "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+        ],
+        [
+            "4920776f756c64206c696b65207468652047656e6572616c2047617527732043",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8",
+            "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+        ],
+        [
+            "4920776f756c64206c696b65207468652047656e6572616c2047617527732043\
+             6869636b656e2c20706c656173652c",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5b3\
+             fffd940c16a18c1b5549d2f838029e",
+            "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+             39312523a78662d5be7fcbcc98ebf5",
+            "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+             39312523a78662d5be7fcbcc98ebf5",
+        ],
+        [
+            "4920776f756c64206c696b65207468652047656e6572616c2047617527732043\
+             6869636b656e2c20706c656173652c20",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             9dad8bbb96c4cdc03bc103e1a194bbd8",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             9dad8bbb96c4cdc03bc103e1a194bbd8",
+            "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8\
+             39312523a78662d5be7fcbcc98ebf5a8",
+        ],
+        [
+            "4920776f756c64206c696b65207468652047656e6572616c2047617527732043\
+             6869636b656e2c20706c656173652c20616e6420776f6e746f6e20736f75702e",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             9dad8bbb96c4cdc03bc103e1a194bbd84807efe836ee89a526730dbc2f7bc840",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             9dad8bbb96c4cdc03bc103e1a194bbd84807efe836ee89a526730dbc2f7bc840",
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+        ],
+    ];
+
+    check_cbc_cs("636869636b656e207465726979616b69", &vectors);
+}
+
+#[test]
+fn cbc_cs_single_block() {
+    // A single block is encrypted with plain CBC by all variants.
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("000102030405060708090a0b0c0d0e0f");
+    let mut aes = AES::new(Size::AES128, Mode::CBCCS1, key.as_slice(), iv.as_slice());
+
+    let mut buf = as_vec("6bc1bee22e409f96e93d7e117393172a");
+    aes_cbc_cs1_encrypt_buffer(&mut aes, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("7649abac8119b246cee98e9b12e9197d"));
+    aes.set_iv(iv.as_slice()).unwrap();
+    aes_cbc_cs3_decrypt_buffer(&mut aes, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("6bc1bee22e409f96e93d7e117393172a"));
+}
+
+#[test]
+fn cbc_cs_chains_across_calls() {
+    // With whole blocks CBC-CS1 is CBC, so two calls give the same
+    // ciphertext as one call over both buffers.
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("000102030405060708090a0b0c0d0e0f");
+    let plain: Vec<u8> = (0..64).collect();
+    let mut aes = AES::new(Size::AES128, Mode::CBCCS1, key.as_slice(), iv.as_slice());
+
+    let mut whole = plain.clone();
+    aes_cbc_cs1_encrypt_buffer(&mut aes, &mut whole).unwrap();
+    assert_eq!(aes.iv[..], whole[48..]);
+
+    aes.set_iv(iv.as_slice()).unwrap();
+    let mut parts = plain.clone();
+    for part in parts.chunks_mut(32) {
+        aes_cbc_cs1_encrypt_buffer(&mut aes, part).unwrap();
+    }
+    assert_eq!(parts, whole);
+
+    aes.set_iv(iv.as_slice()).unwrap();
+    for part in parts.chunks_mut(32) {
+        aes_cbc_cs1_decrypt_buffer(&mut aes, part).unwrap();
+    }
+    assert_eq!(parts, plain);
+}
+
+#[test]
+fn padding_schemes() {
+    let vectors = [
+        (Padding::PKCS7, "01020304", "010203040c0c0c0c0c0c0c0c0c0c0c0c"),
+        (Padding::ISO7816, "01020304", "01020304800000000000000000000000"),
+        (Padding::X923, "01020304", "0102030400000000000000000000000c"),
+        (Padding::Zero, "01020304", "01020304000000000000000000000000"),
+        (
+            Padding::PKCS7,
+            "000102030405060708090a0b0c0d0e0f",
+            "000102030405060708090a0b0c0d0e0f10101010101010101010101010101010",
+        ),
+        (
+            Padding::ISO7816,
+            "000102030405060708090a0b0c0d0e",
+            "000102030405060708090a0b0c0d0e80",
+        ),
+        (
+            Padding::Zero,
+            "000102030405060708090a0b0c0d0e0f",
+            "000102030405060708090a0b0c0d0e0f",
+        ),
+        (Padding::Zero, "", ""),
+    ];
+
+    for &(padding, data, padded) in vectors.iter() {
+        let mut buf = as_vec(data);
+        pad(padding, &mut buf).unwrap();
+        assert_eq!(buf, as_vec(padded));
+
+        unpad(padding, &mut buf).unwrap();
+        assert_eq!(buf, as_vec(data));
+    }
+
+    // ISO 10126 pads with random bytes, only the length is fixed.
+    let mut buf = as_vec("01020304");
+    pad(Padding::ISO10126, &mut buf).unwrap();
+    assert_eq!(buf.len(), 16);
+    assert_eq!(buf[15], 12);
+    unpad(Padding::ISO10126, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("01020304"));
+}
+
+#[test]
+fn padding_rejects_malformed_input() {
+    let vectors = [
+        (Padding::PKCS7, "0102030405060708090a0b0c0d0e0f00"),
+        (Padding::PKCS7, "0102030405060708090a0b0c0d0e0f11"),
+        (Padding::PKCS7, "0102030405060708090a0b0c0d040304"),
+        (Padding::PKCS7, "01020304050607080909090909090909"),
+        (Padding::ISO7816, "01020304050607080900000000000000"),
+        (Padding::ISO7816, "00000000000000000000000000000000"),
+        (Padding::X923, "01020304050607080900000001000007"),
+        (Padding::X923, "0102030405060708090a0b0c0d0e0f20"),
+        (Padding::ISO10126, "0102030405060708090a0b0c0d0e0f00"),
+        (Padding::PKCS7, "0102030405060708090a0b0c0d0e01"),
+        (Padding::PKCS7, ""),
+    ];
+
+    for &(padding, data) in vectors.iter() {
+        let mut buf = as_vec(data);
+        assert_eq!(unpad(padding, &mut buf), Err(Error::BadPadding));
+        assert_eq!(buf, as_vec(data));
+    }
+}
+
+#[test]
+fn cbc_padded() {
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("000102030405060708090a0b0c0d0e0f");
+    let mut aes = AES::new(Size::AES128, Mode::CBC, key.as_slice(), iv.as_slice());
+
+    // F.2.1 CBC-AES128.Encrypt, with a full block of PKCS#7 padding.
+    let mut buf = as_vec("6bc1bee22e409f96e93d7e117393172a");
+    aes_cbc_encrypt_padded(&mut aes, Padding::PKCS7, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        as_vec("7649abac8119b246cee98e9b12e9197d8964e0b149c10b7b682e6e39aaeb731c")
+    );
+
+    aes.set_iv(iv.as_slice()).unwrap();
+    aes_cbc_decrypt_padded(&mut aes, Padding::PKCS7, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("6bc1bee22e409f96e93d7e117393172a"));
+
+    // Any change to the last block makes the padding invalid.
+    let mut buf = as_vec("7649abac8119b246cee98e9b12e9197d8964e0b149c10b7b682e6e39aaeb731d");
+    aes.set_iv(iv.as_slice()).unwrap();
+    assert_eq!(
+        aes_cbc_decrypt_padded(&mut aes, Padding::PKCS7, &mut buf),
+        Err(Error::BadPadding)
+    );
+
+    let mut buf = as_vec("7649abac8119b246cee98e9b12e919");
+    assert_eq!(
+        aes_cbc_decrypt_padded(&mut aes, Padding::PKCS7, &mut buf),
+        Err(Error::BadPadding)
+    );
+}
+
+#[test]
+fn ecb_padded() {
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let aes = AES::new(Size::AES128, Mode::ECB, key.as_slice(), &[0u8; 16]);
+
+    // F.1.1 ECB-AES128.Encrypt, with a full block of PKCS#7 padding.
+    let mut buf = as_vec("6bc1bee22e409f96e93d7e117393172a");
+    aes_ecb_encrypt_padded(&aes, Padding::PKCS7, &mut buf).unwrap();
+    assert_eq!(
+        buf,
+        as_vec("3ad77bb40d7a3660a89ecaf32466ef97a254be88e037ddd9d79fb6411c3f9df8")
+    );
+    aes_ecb_decrypt_padded(&aes, Padding::PKCS7, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("6bc1bee22e409f96e93d7e117393172a"));
+
+    let mut buf = as_vec("6bc1bee22e409f96e93d7e11739317");
+    aes_ecb_encrypt_padded(&aes, Padding::ISO7816, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("c5b361a9a7b2643a00d83df49c318e56"));
+    aes_ecb_decrypt_padded(&aes, Padding::ISO7816, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("6bc1bee22e409f96e93d7e11739317"));
+}
+
+#[test]
+fn try_new_rejects_bad_lengths() {
+    let iv = [0u8; 16];
+    assert!(AES::try_new(Size::AES128, Mode::CBC, &[0u8; 16], &iv).is_ok());
+    assert!(AES::try_new(Size::AES192, Mode::CBC, &[0u8; 24], &iv).is_ok());
+    assert!(AES::try_new(Size::AES256, Mode::CBC, &[0u8; 32], &iv).is_ok());
+
+    let bad_keys = [(Size::AES128, 24), (Size::AES192, 16), (Size::AES256, 31)];
+    for &(size, len) in bad_keys.iter() {
+        assert_eq!(
+            AES::try_new(size, Mode::CBC, &vec![0u8; len], &iv).err(),
+            Some(Error::BadKeyLength)
+        );
+    }
+    assert_eq!(
+        AES::try_new(Size::AES128, Mode::CBC, &[0u8; 16], &[0u8; 12]).err(),
+        Some(Error::BadIvLength)
+    );
+
+    let mut aes = AES::new(Size::AES128, Mode::CBC, &[0u8; 16], &iv);
+    assert_eq!(aes.set_key(&[0u8; 32]), Err(Error::BadKeyLength));
+    assert_eq!(aes.set_iv(&[0u8; 15]), Err(Error::BadIvLength));
+    assert_eq!(aes.iv, iv);
+
+    assert_eq!(
+        XTS::try_new(Size::AES128, &[0u8; 16], &[0u8; 8]).err(),
+        Some(Error::BadKeyLength)
+    );
+    assert_eq!(SIV::try_new(&[0u8; 16]).err(), Some(Error::BadKeyLength));
+}
+
+#[test]
+fn set_key_replaces_key() {
+    // F.1.1 ECB-AES128.Encrypt, after starting out with another key.
+    let mut aes = AES::new(Size::AES128, Mode::ECB, &[0u8; 16], &[0u8; 16]);
+    aes.set_key(as_vec("2b7e151628aed2a6abf7158809cf4f3c").as_slice()).unwrap();
+
+    let mut buf = as_vec("6bc1bee22e409f96e93d7e117393172a");
+    aes_ecb_encrypt(&aes, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("3ad77bb40d7a3660a89ecaf32466ef97"));
+}
+
+#[test]
+fn ctr_counter_exhausted() {
+    let mut iv = [0xffu8; 16];
+    iv[15] = 0xfe;
+    let mut aes = AES::new(Size::AES128, Mode::CTR, &[0u8; 16], &iv);
+
+    // Two blocks are left, the counter must not wrap around.
+    let mut buf = [0u8; 33];
+    assert_eq!(
+        aes_ctr_xcrypt_buffer(&mut aes, &mut buf),
+        Err(Error::CounterExhausted)
+    );
+    assert_eq!(buf, [0u8; 33]);
+    aes_ctr_xcrypt_buffer(&mut aes, &mut buf[..32]).unwrap();
+}
+
+#[test]
+fn modes_reject_bad_parameters() {
+    let aes = AES::new(Size::AES128, Mode::GCM, &[0u8; 16], &[0u8; 16]);
+    let mut buf = [0u8; 15];
+
+    let mut tag = [0u8; 16];
+    assert_eq!(
+        aes_gcm_seal(&aes, &[], &[], &mut buf, &mut tag),
+        Err(Error::BadIvLength)
+    );
+    assert_eq!(
+        aes_gcm_seal(&aes, &[0u8; 12], &[], &mut buf, &mut tag[..10]),
+        Err(Error::BadTagLength)
+    );
+    assert_eq!(
+        aes_ccm_seal(&aes, &[0u8; 14], &[], &mut buf, &mut tag),
+        Err(Error::BadIvLength)
+    );
+    assert_eq!(
+        aes_ccm_seal(&aes, &[0u8; 13], &[], &mut buf, &mut tag[..5]),
+        Err(Error::BadTagLength)
+    );
+    assert_eq!(
+        aes_ocb_seal(&aes, &[0u8; 16], &[], &mut buf, &mut tag),
+        Err(Error::BadIvLength)
+    );
+    assert_eq!(
+        aes_eax_seal(&aes, &[0u8; 16], &[], &mut buf, &mut tag[..0]),
+        Err(Error::BadTagLength)
+    );
+    assert_eq!(buf, [0u8; 15]);
+
+    let gcm_siv = AES::new(Size::AES192, Mode::GCMSIV, &[0u8; 24], &[0u8; 16]);
+    assert_eq!(
+        aes_gcm_siv_seal(&gcm_siv, &[0u8; 12], &[], &mut buf, &mut tag),
+        Err(Error::BadKeyLength)
+    );
+
+    let mut cbc = AES::new(Size::AES128, Mode::CBC, &[0u8; 16], &[0u8; 16]);
+    assert_eq!(
+        aes_cbc_encrypt_buffer(&mut cbc, &mut buf),
+        Err(Error::MisalignedBuffer)
+    );
+    assert_eq!(
+        aes_cbc_cs3_encrypt_buffer(&mut cbc, &mut buf),
+        Err(Error::MisalignedBuffer)
+    );
+    let xts = XTS::new(Size::AES128, &[0u8; 16], &[1u8; 16]);
+    assert_eq!(
+        aes_xts_encrypt_sector(&xts, 0, &mut buf),
+        Err(Error::MisalignedBuffer)
+    );
+    assert_eq!(aes_kw_wrap(&aes, &[0u8; 20]), Err(Error::MisalignedBuffer));
+    assert_eq!(aes_kwp_wrap(&aes, &[]), Err(Error::MisalignedBuffer));
+    assert_eq!(buf, [0u8; 15]);
+}
+
+#[test]
+fn with_key_infers_size() {
+    // F.1.1, F.1.3 and F.1.5, the first block of ECB-AES128/192/256.Encrypt
+    let vectors = [
+        (
+            Size::AES128,
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3ad77bb40d7a3660a89ecaf32466ef97",
+        ),
+        (
+            Size::AES192,
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "bd334f1d6e45f25ff712a214571fa5cc",
+        ),
+        (
+            Size::AES256,
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "f3eed1bdb5d2a03c064b5a7e3db181f8",
+        ),
+    ];
+
+    for &(size, key, cipher) in vectors.iter() {
+        let aes = AES::with_key(Mode::ECB, as_vec(key).as_slice(), &[0u8; 16]).unwrap();
+        assert_eq!(aes.size, size);
+        assert_eq!(size.key_len(), as_vec(key).len());
+
+        let mut buf = as_vec("6bc1bee22e409f96e93d7e117393172a");
+        aes_ecb_encrypt(&aes, &mut buf).unwrap();
+        assert_eq!(buf, as_vec(cipher));
+    }
+
+    assert_eq!(
+        AES::with_key(Mode::ECB, &[0u8; 20], &[0u8; 16]).err(),
+        Some(Error::BadKeyLength)
+    );
+    assert_eq!(Size::from_key_len(0), Err(Error::BadKeyLength));
+}
+
+#[test]
+fn array_constructors() {
+    let iv = [0u8; 16];
+    assert_eq!(AES::new_128(Mode::ECB, &[0u8; 16], &iv).size, Size::AES128);
+    assert_eq!(AES::new_192(Mode::ECB, &[0u8; 24], &iv).size, Size::AES192);
+    assert_eq!(AES::new_256(Mode::ECB, &[0u8; 32], &iv).size, Size::AES256);
+
+    // F.1.1 ECB-AES128.Encrypt
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&as_vec("2b7e151628aed2a6abf7158809cf4f3c"));
+    let aes = AES::new_128(Mode::ECB, &key, &iv);
+    let mut buf = as_vec("6bc1bee22e409f96e93d7e117393172a");
+    aes_ecb_encrypt(&aes, &mut buf).unwrap();
+    assert_eq!(buf, as_vec("3ad77bb40d7a3660a89ecaf32466ef97"));
+}
+
+#[test]
+fn encrypt_dispatches_on_mode() {
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("000102030405060708090a0b0c0d0e0f");
+    let plain = as_vec(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+
+    // Every mode gives the same result as its own function.
+    for &mode in [Mode::ECB, Mode::CBC, Mode::CFB128, Mode::OFB, Mode::CTR].iter() {
+        let mut aes = AES::new(Size::AES128, mode, key.as_slice(), iv.as_slice());
+        let mut buf = plain.clone();
+        aes.encrypt(&mut buf).unwrap();
+
+        let mut expected = plain.clone();
+        let mut direct = AES::new(Size::AES128, mode, key.as_slice(), iv.as_slice());
+        match mode {
+            Mode::ECB => aes_ecb_encrypt(&direct, &mut expected).unwrap(),
+            Mode::CBC => aes_cbc_encrypt_buffer(&mut direct, &mut expected).unwrap(),
+            Mode::CFB128 => aes_cfb128_encrypt_buffer(&mut direct, &mut expected),
+            Mode::OFB => aes_ofb_xcrypt_buffer(&mut direct, &mut expected),
+            _ => aes_ctr_xcrypt_buffer(&mut direct, &mut expected).unwrap(),
+        }
+        assert_eq!(buf, expected);
+
+        aes.set_iv(iv.as_slice()).unwrap();
+        aes.decrypt(&mut buf).unwrap();
+        assert_eq!(buf, plain);
+    }
+
+    // RFC 3962 Appendix B, the first example
+    let mut aes = AES::with_key(
+        Mode::CBCCS3,
+        as_vec("636869636b656e207465726979616b69").as_slice(),
+        &[0u8; 16],
+    )
+    .unwrap();
+    let mut buf = as_vec("4920776f756c64206c696b652074686520");
+    aes.encrypt(&mut buf).unwrap();
+    assert_eq!(buf, as_vec("c6353568f2bf8cb4d8a580362da7ff7f97"));
+    aes.set_iv(&[0u8; 16]).unwrap();
+    aes.decrypt(&mut buf).unwrap();
+    assert_eq!(buf, as_vec("4920776f756c64206c696b652074686520"));
+}
+
+#[test]
+fn encrypt_chains_cbc_cs_messages() {
+    let key = as_vec("636869636b656e207465726979616b69");
+    let plain = as_vec("4920776f756c64206c696b652074686520");
+    for &mode in [Mode::CBCCS1, Mode::CBCCS2, Mode::CBCCS3].iter() {
+        let mut aes = AES::with_key(mode, key.as_slice(), &[0u8; 16]).unwrap();
+        let mut first = plain.clone();
+        aes.encrypt(&mut first).unwrap();
+        let mut second = plain.clone();
+        aes.encrypt(&mut second).unwrap();
+        assert_ne!(first, second);
+
+        let mut aes = AES::with_key(mode, key.as_slice(), &[0u8; 16]).unwrap();
+        aes.decrypt(&mut first).unwrap();
+        aes.decrypt(&mut second).unwrap();
+        assert_eq!(first, plain);
+        assert_eq!(second, plain);
+    }
+}
+
+#[test]
+fn encrypt_rejects_modes_with_extra_parameters() {
+    let mut buf = [0u8; 16];
+    for &mode in [Mode::GCM, Mode::XTS, Mode::KW, Mode::OCB].iter() {
+        let mut aes = AES::new(Size::AES128, mode, &[0u8; 16], &[0u8; 16]);
+        assert_eq!(aes.encrypt(&mut buf), Err(Error::UnsupportedMode));
+        assert_eq!(aes.decrypt(&mut buf), Err(Error::UnsupportedMode));
+    }
+    assert_eq!(buf, [0u8; 16]);
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn key_schedule_streams() {
+    assert_send_sync::<KeySchedule>();
+
+    // F.2.1, F.5.1 and F.3.13, CBC, CTR and CFB128-AES128.Encrypt
+    let key = KeySchedule::with_key(&as_vec("2b7e151628aed2a6abf7158809cf4f3c")).unwrap();
+    let mut iv = [0u8; 16];
+    iv.copy_from_slice(&as_vec("000102030405060708090a0b0c0d0e0f"));
+    let mut counter = [0u8; 16];
+    counter.copy_from_slice(&as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"));
+    let plain = as_vec(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+    assert_eq!(key.size(), Size::AES128);
+
+    let mut buf = plain.clone();
+    let mut cbc = CBC::new(&key, &iv);
+    for chunk in buf.chunks_mut(32) {
+        cbc.encrypt(chunk).unwrap();
+    }
+    assert_eq!(
+        buf,
+        as_vec(
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+             73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7"
+        )
+    );
+    assert_eq!(cbc.encrypt(&mut buf[..15]), Err(Error::MisalignedBuffer));
+    let mut cbc = CBC::new(&key, &iv);
+    cbc.decrypt(&mut buf).unwrap();
+    assert_eq!(buf, plain);
+
+    let mut ctr = CTR::new(&key, &counter);
+    for chunk in buf.chunks_mut(5) {
+        ctr.xcrypt(chunk).unwrap();
+    }
+    assert_eq!(
+        buf,
+        as_vec(
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+             5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"
+        )
+    );
+    let mut ctr = CTR::new(&key, &counter);
+    ctr.xcrypt(&mut buf).unwrap();
+    assert_eq!(buf, plain);
+
+    let mut cfb = CFB::new(&key, &iv);
+    for chunk in buf.chunks_mut(7) {
+        cfb.encrypt(chunk);
+    }
+    assert_eq!(
+        buf,
+        as_vec(
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6"
+        )
+    );
+    let mut cfb = CFB::new(&key, &iv);
+    cfb.decrypt(&mut buf);
+    assert_eq!(buf, plain);
+
+    // The schedule of a context is the same key.
+    let aes = AES::new(Size::AES128, Mode::ECB, &as_vec("2b7e151628aed2a6abf7158809cf4f3c"), &iv);
+    let mut block = [0u8; 16];
+    block.copy_from_slice(&plain[..16]);
+    KeySchedule::from(&aes).encrypt_block(&mut block);
+    assert_eq!(block.to_vec(), as_vec("3ad77bb40d7a3660a89ecaf32466ef97"));
+    key.decrypt_block(&mut block);
+    assert_eq!(block.to_vec(), plain[..16].to_vec());
+}
+
+#[test]
+fn key_schedule_shared_between_threads() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let key = Arc::new(KeySchedule::new(Size::AES128, &[7u8; 16]));
+    let mut expected = [0u8; 64];
+    CTR::new(&key, &[0u8; 16]).xcrypt(&mut expected).unwrap();
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let key = Arc::clone(&key);
+            thread::spawn(move || {
+                let mut buf = [0u8; 64];
+                CTR::new(&key, &[0u8; 16]).xcrypt(&mut buf).unwrap();
+                buf
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap()[..], expected[..]);
+    }
+
+    assert_eq!(
+        KeySchedule::try_new(Size::AES256, &[0u8; 16]).err(),
+        Some(Error::BadKeyLength)
+    );
+}
+
+#[test]
+fn ctr_chunks_and_seek() {
+    // F.5.1 CTR-AES128.Encrypt
+    let key = as_vec("2b7e151628aed2a6abf7158809cf4f3c");
+    let iv = as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let plain = as_vec(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+         30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+    );
+    let cipher = as_vec(
+        "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+         5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee",
+    );
+
+    let mut aes = AES::new(Size::AES128, Mode::CTR, &key, &iv);
+    let mut buf = plain.clone();
+    for chunk in buf.chunks_mut(7) {
+        aes_ctr_xcrypt_buffer(&mut aes, chunk).unwrap();
+    }
+    assert_eq!(buf, cipher);
+
+    // Reading at any position gives the matching plaintext bytes.
+    for &(pos, len) in [(0, 64), (5, 11), (16, 16), (17, 30), (63, 1), (40, 24)].iter() {
+        let mut part = cipher[pos..pos + len].to_vec();
+        aes.seek(pos as u64).unwrap();
+        aes_ctr_xcrypt_buffer(&mut aes, &mut part).unwrap();
+        assert_eq!(part, &plain[pos..pos + len]);
+    }
+
+    // Assigning a different IV starts a new stream there.
+    aes_ctr_xcrypt_buffer(&mut aes, &mut [0u8; 5]).unwrap();
+    aes.iv.copy_from_slice(&as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdff01"));
+    let mut part = cipher[32..].to_vec();
+    aes_ctr_xcrypt_buffer(&mut aes, &mut part[..3]).unwrap();
+    aes_ctr_xcrypt_buffer(&mut aes, &mut part[3..]).unwrap();
+    assert_eq!(part, &plain[32..]);
+    aes.iv.copy_from_slice(&as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdff01"));
+    let mut part = cipher[50..].to_vec();
+    aes.seek(18).unwrap();
+    aes_ctr_xcrypt_buffer(&mut aes, &mut part).unwrap();
+    assert_eq!(part, &plain[50..]);
+
+    // Restarting at the counter block the stream already holds needs set_iv.
+    aes.set_iv(&as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdff01")).unwrap();
+    aes_ctr_xcrypt_buffer(&mut aes, &mut [0u8; 5]).unwrap();
+    assert_eq!(aes.iv[..], as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdff02")[..]);
+    aes.set_iv(&as_vec("f0f1f2f3f4f5f6f7f8f9fafbfcfdff02")).unwrap();
+    let mut part = cipher[48..].to_vec();
+    aes_ctr_xcrypt_buffer(&mut aes, &mut part).unwrap();
+    assert_eq!(part, &plain[48..]);
+
+    let schedule = KeySchedule::from(&aes);
+    let mut counter = [0u8; 16];
+    counter.copy_from_slice(&iv);
+    let mut ctr = CTR::new(&schedule, &counter);
+    let mut part = cipher[37..].to_vec();
+    ctr.seek(37).unwrap();
+    ctr.xcrypt(&mut part).unwrap();
+    assert_eq!(part, &plain[37..]);
+
+    // The end of the keystream is a valid position, past it is not.
+    let mut ctr = CTR::new(&schedule, &[0xffu8; 16]);
+    assert_eq!(ctr.seek(17), Err(Error::CounterExhausted));
+    assert_eq!(ctr.seek(32), Err(Error::CounterExhausted));
+    ctr.seek(16).unwrap();
+    ctr.xcrypt(&mut []).unwrap();
+    assert_eq!(ctr.xcrypt(&mut [0u8; 1]), Err(Error::CounterExhausted));
+    ctr.seek(15).unwrap();
+    ctr.xcrypt(&mut [0u8; 1]).unwrap();
+    assert_eq!(ctr.xcrypt(&mut [0u8; 1]), Err(Error::CounterExhausted));
+
+    let mut cbc = AES::new(Size::AES128, Mode::CBC, &key, &iv);
+    assert_eq!(cbc.seek(16), Err(Error::UnsupportedMode));
+}
+
+// RFC 3686 Test Vector #2
+#[test]
+fn ctr_rfc3686() {
+    let key = KeySchedule::with_key(&as_vec("7e24067817fae0d743d6ce1f32539163")).unwrap();
+    let nonce = [0x00, 0x6c, 0xb6, 0xdb];
+    let iv = [0xc0, 0x54, 0x3b, 0x59, 0xda, 0x48, 0xd9, 0x0b];
+    let block = rfc3686_counter_block(&nonce, &iv);
+    assert_eq!(block.to_vec(), as_vec("006cb6dbc0543b59da48d90b00000001"));
+
+    let mut buf = as_vec("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+    CTR::with_layout(&key, &block, CounterLayout::NONCE_96).xcrypt(&mut buf).unwrap();
+    assert_eq!(
+        buf,
+        as_vec("5104a106168a72d9790d41ee8edad388eb2e1efc46da57c8fce630df9141be28")
+    );
+}
+
+#[test]
+fn ctr_counter_layouts() {
+    let key = KeySchedule::new(Size::AES128, &[0u8; 16]);
+    let keystream = |counter: &[u8; 16]| {
+        let mut block = *counter;
+        key.encrypt_block(&mut block);
+        block
+    };
+
+    // A 64-bit little-endian counter at the start of the block carries into
+    // its second byte and leaves the nonce in the other half alone.
+    let layout = CounterLayout::new(0, 8, Endian::Little).unwrap();
+    let mut counter = [0x55u8; 16];
+    counter[..8].copy_from_slice(&[0xff, 0, 0, 0, 0, 0, 0, 0]);
+    let mut next = counter;
+    next[..8].copy_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    let mut buf = [0u8; 32];
+    CTR::with_layout(&key, &counter, layout).xcrypt(&mut buf).unwrap();
+    assert_eq!(buf[..16], keystream(&counter));
+    assert_eq!(buf[16..], keystream(&next));
+
+    // A 32-bit counter with two values left runs out instead of wrapping
+    // into the nonce or back to zero.
+    let mut counter = [0xaau8; 16];
+    counter[12..].copy_from_slice(&[0xff, 0xff, 0xff, 0xfe]);
+    let mut ctr = CTR::with_layout(&key, &counter, CounterLayout::NONCE_96);
+    let mut buf = [0u8; 33];
+    assert_eq!(ctr.xcrypt(&mut buf), Err(Error::CounterExhausted));
+    assert_eq!(buf, [0u8; 33]);
+    ctr.xcrypt(&mut buf[..20]).unwrap();
+    ctr.xcrypt(&mut buf[20..32]).unwrap();
+    assert_eq!(ctr.xcrypt(&mut buf[32..]), Err(Error::CounterExhausted));
+    assert_eq!(ctr.seek(33), Err(Error::CounterExhausted));
+    ctr.seek(32).unwrap();
+    ctr.xcrypt(&mut []).unwrap();
+    assert_eq!(ctr.xcrypt(&mut buf[32..]), Err(Error::CounterExhausted));
+    ctr.seek(17).unwrap();
+    ctr.xcrypt(&mut buf[17..32]).unwrap();
+
+    let mut aes = AES::new(Size::AES128, Mode::CTR, &[0u8; 16], &counter);
+    aes.set_counter_layout(CounterLayout::NONCE_96);
+    let mut buf = [0u8; 33];
+    assert_eq!(
+        aes_ctr_xcrypt_buffer(&mut aes, &mut buf),
+        Err(Error::CounterExhausted)
+    );
+    aes_ctr_xcrypt_buffer(&mut aes, &mut buf[..32]).unwrap();
+    assert_eq!(buf[..16], keystream(&counter));
+    assert_eq!(aes.iv[..12], counter[..12]);
+    assert_eq!(
+        aes_ctr_xcrypt_buffer(&mut aes, &mut buf[..1]),
+        Err(Error::CounterExhausted)
+    );
+
+    assert_eq!(
+        CounterLayout::new(10, 8, Endian::Big),
+        Err(Error::BadCounterLayout)
+    );
+    assert_eq!(
+        CounterLayout::new(0, 0, Endian::Big),
+        Err(Error::BadCounterLayout)
+    );
+}
+
+#[test]
+fn batched_blocks_match_single_blocks() {
+    // Seven blocks, so the multi-block code has a partial group at the end.
+    let aes = AES::new(Size::AES192, Mode::ECB, &[0x5au8; 24], &[0u8; 16]);
+    let plain: Vec<u8> = (0..112).map(|i| (i * 37 + 11) as u8).collect();
+
+    let mut buf = plain.clone();
+    aes_ecb_encrypt(&aes, &mut buf).unwrap();
+    for (i, chunk) in plain.chunks(16).enumerate() {
+        let mut block = chunk.to_vec();
+        aes_ecb_encrypt(&aes, &mut block).unwrap();
+        assert_eq!(block, &buf[16 * i..16 * (i + 1)]);
+    }
+
+    let mut cbc = AES::new(Size::AES192, Mode::CBC, &[0x5au8; 24], &[3u8; 16]);
+    aes_cbc_encrypt_buffer(&mut cbc, &mut buf).unwrap();
+    let mut single = buf.clone();
+    cbc.set_iv(&[3u8; 16]).unwrap();
+    aes_cbc_decrypt_buffer(&mut cbc, &mut buf).unwrap();
+    cbc.set_iv(&[3u8; 16]).unwrap();
+    for chunk in single.chunks_mut(16) {
+        aes_cbc_decrypt_buffer(&mut cbc, chunk).unwrap();
+    }
+    assert_eq!(buf, single);
+
+    aes_ecb_decrypt(&aes, &mut buf).unwrap();
+    assert_eq!(buf, plain);
+}
+
+// Encrypts the same data with ECB, CBC, CTR and GCM under all key sizes.
+fn backend_outputs() -> Vec<Vec<u8>> {
+    // Long enough for the 16 block groups of the VAES code plus a few more,
+    // and 15 blocks for its groups of 8 and 4 blocks.
+    let plain: Vec<u8> = (0..600).map(|i| (i * 73 + 5) as u8).collect();
+    let mut outputs = Vec::new();
+    for &(size, len) in [(Size::AES128, 16), (Size::AES192, 24), (Size::AES256, 32)].iter() {
+        let key: Vec<u8> = (0..len).map(|i| (i * 11 + 1) as u8).collect();
+
+        let ecb = AES::new(size, Mode::ECB, &key, &[0u8; 16]);
+        for &n in [592, 240].iter() {
+            let mut buf = plain[..n].to_vec();
+            aes_ecb_encrypt(&ecb, &mut buf).unwrap();
+            outputs.push(buf.clone());
+            aes_ecb_decrypt(&ecb, &mut buf).unwrap();
+            outputs.push(buf);
+        }
+
+        let mut cbc = AES::new(size, Mode::CBC, &key, &[9u8; 16]);
+        let mut buf = plain[..592].to_vec();
+        aes_cbc_decrypt_buffer(&mut cbc, &mut buf).unwrap();
+        outputs.push(buf);
+
+        let mut ctr = AES::new(size, Mode::CTR, &key, &[0xfeu8; 16]);
+        let mut buf = plain[..599].to_vec();
+        aes_ctr_xcrypt_buffer(&mut ctr, &mut buf[..5]).unwrap();
+        aes_ctr_xcrypt_buffer(&mut ctr, &mut buf[5..]).unwrap();
+        outputs.push(buf);
+
+        let mut buf = plain.clone();
+        let mut tag = [0u8; 16];
+        aes_gcm_seal(&ecb, &[1u8; 12], &plain[..37], &mut buf, &mut tag).unwrap();
+        outputs.push(buf);
+        outputs.push(tag.to_vec());
+    }
+    outputs
+}
+
+#[test]
+fn backends_agree() {
+    Backend::Soft.select().unwrap();
+    let expected = backend_outputs();
+
+    let backends = [
+        Backend::Bitslice,
+        Backend::AesNi,
+        Backend::Vaes,
+        Backend::TTable,
+        Backend::Vperm,
+    ];
+    for &backend in backends.iter() {
+        if !backend.is_available() {
+            assert_eq!(backend.select(), Err(Error::UnsupportedBackend));
+            continue;
+        }
+        backend.select().unwrap();
+        assert_eq!(Backend::selected(), backend);
+        assert!(backend_outputs() == expected, "{:?} differs", backend);
+    }
+
+    Backend::detect().select().unwrap();
+}